@@ -0,0 +1,475 @@
+mod noise;
+mod pulse;
+mod resample;
+mod wave;
+
+// The APU is clocked at 1 MiHz in either CPU speed.
+pub const APU_CLOCK: u32 = 1 << 20;
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+// Largest change rate control makes to the output rate, small enough not to be heard as pitch.
+const MAX_RATE_DELTA: f64 = 0.005;
+
+// Counts down at 256 Hz while enabled and turns the channel off when it reaches zero.
+struct Length {
+    max: u16,
+    counter: u16,
+    enabled: bool,
+}
+impl Length {
+    fn new(max: u16) -> Self {
+        Self {
+            max,
+            counter: 0,
+            enabled: false,
+        }
+    }
+    fn load(&mut self, val: u8) {
+        self.counter = self.max - (val as u16 & (self.max - 1));
+    }
+    // Returns true when the channel should be turned off.
+    fn clock(&mut self) -> bool {
+        if !self.enabled || self.counter == 0 {
+            return false;
+        }
+        self.counter -= 1;
+        self.counter == 0
+    }
+    // Handles the length enable and trigger bits of NRx4. Enabling the counter while the next
+    // frame sequencer step won't clock it clocks it once straight away, and a trigger reloads
+    // an empty counter. Returns true when the channel should be turned off.
+    fn write(&mut self, enable: bool, trigger: bool, first_half: bool) -> bool {
+        let mut off = false;
+        if !self.enabled && enable && first_half && self.counter > 0 {
+            self.counter -= 1;
+            off = self.counter == 0 && !trigger;
+        }
+        self.enabled = enable;
+        if trigger && self.counter == 0 {
+            self.counter = self.max;
+            if enable && first_half {
+                self.counter -= 1;
+            }
+        }
+        off
+    }
+}
+
+// NRx2 of the pulse and noise channels. The upper five bits also power the channel's DAC.
+#[derive(Default)]
+struct Envelope {
+    reg: u8,
+    volume: u8,
+    timer: u8,
+}
+impl Envelope {
+    fn dac_enabled(&self) -> bool {
+        self.reg & 0xf8 > 0
+    }
+    fn trigger(&mut self) {
+        self.volume = self.reg >> 4;
+        self.timer = self.reg & 0x07;
+    }
+    fn clock(&mut self) {
+        let period = self.reg & 0x07;
+        if period == 0 {
+            return;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return;
+        }
+        self.timer = period;
+        if self.reg & 0x08 > 0 && self.volume < 15 {
+            self.volume += 1;
+        } else if self.reg & 0x08 == 0 && self.volume > 0 {
+            self.volume -= 1;
+        }
+    }
+}
+
+// The capacitor between the mixer and the output jack, which drains the DC offset of the DACs.
+// It charges faster on the CGB. The factors are per 4 MiHz cycle.
+struct HighPass {
+    charge: f32,
+    capacitor: [f32; 2],
+}
+impl HighPass {
+    fn new(cgb: bool, rate: u32) -> Self {
+        let factor: f64 = if cgb { 0.998943 } else { 0.999958 };
+        Self {
+            charge: factor.powf(4194304.0 / rate as f64) as f32,
+            capacitor: [0.0; 2],
+        }
+    }
+    fn filter(&mut self, input: [f32; 2]) -> [f32; 2] {
+        let mut output = [0.0; 2];
+        for ((output, capacitor), input) in output.iter_mut().zip(&mut self.capacitor).zip(input) {
+            *output = input - *capacitor;
+            *capacitor = input - *output * self.charge;
+        }
+        output
+    }
+}
+
+// Four channels mixed into a stereo pair by NR51 and scaled by the NR50 master volume. The
+// mix is resampled to the host's rate and filtered into an interleaved left/right buffer.
+pub struct Apu {
+    cgb: bool,
+    power: bool,
+    nr50: u8,
+    nr51: u8,
+    // Next frame sequencer step. Even steps clock length, 2 and 6 the sweep and 7 envelopes.
+    step: u8,
+    ch1: pulse::Pulse,
+    ch2: pulse::Pulse,
+    ch3: wave::Wave,
+    ch4: noise::Noise,
+    sample_rate: u32,
+    resampler: resample::Resampler,
+    high_pass: HighPass,
+    samples: Vec<f32>,
+}
+impl Apu {
+    pub fn new() -> Self {
+        Self {
+            cgb: false,
+            power: false,
+            nr50: 0,
+            nr51: 0,
+            step: 0,
+            ch1: pulse::Pulse::new(true),
+            ch2: pulse::Pulse::new(false),
+            ch3: wave::Wave::new(),
+            ch4: noise::Noise::new(),
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            resampler: resample::Resampler::new(DEFAULT_SAMPLE_RATE as f64),
+            high_pass: HighPass::new(false, DEFAULT_SAMPLE_RATE),
+            samples: Vec::new(),
+        }
+    }
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb;
+        self.high_pass = HighPass::new(self.cgb, self.sample_rate);
+    }
+    pub fn set_sample_rate(&mut self, rate: u32) {
+        self.sample_rate = rate.clamp(1, APU_CLOCK / 2);
+        self.resampler.set_rate(self.sample_rate as f64);
+        self.high_pass = HighPass::new(self.cgb, self.sample_rate);
+    }
+    // Dynamic rate control. With the video locked to the display the emulator produces audio
+    // slightly faster or slower than the host plays it, so the output rate is nudged by up to
+    // MAX_RATE_DELTA to move the `queued` stereo frames in the host's buffer towards `target`.
+    pub fn adjust_sample_rate(&mut self, queued: usize, target: usize) {
+        if target == 0 {
+            return;
+        }
+        let fill = (queued as f64 / target as f64).min(2.0);
+        let rate = self.sample_rate as f64 * (1.0 + MAX_RATE_DELTA * (1.0 - fill));
+        self.resampler.set_rate(rate);
+    }
+    // Interleaved left/right samples in -1.0..=1.0 produced since the last call.
+    pub fn drain_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+    fn first_half(&self) -> bool {
+        self.step & 1 == 1
+    }
+    fn mix(&self) -> [f32; 2] {
+        let outputs = [
+            self.ch1.output(),
+            self.ch2.output(),
+            self.ch3.output(),
+            self.ch4.output(),
+        ];
+        let (mut left, mut right) = (0.0, 0.0);
+        for (i, output) in outputs.into_iter().enumerate() {
+            // Each DAC maps 0 to 1.0 and 15 to -1.0, and outputs nothing while off.
+            let Some(output) = output else {
+                continue;
+            };
+            let analog = 1.0 - output as f32 / 7.5;
+            if self.nr51 & (0x10 << i) > 0 {
+                left += analog;
+            }
+            if self.nr51 & (0x01 << i) > 0 {
+                right += analog;
+            }
+        }
+        let left_volume = ((self.nr50 >> 4) & 0x07) + 1;
+        let right_volume = (self.nr50 & 0x07) + 1;
+        [
+            left * left_volume as f32 / 32.0,
+            right * right_volume as f32 / 32.0,
+        ]
+    }
+    pub fn tick(&mut self) {
+        if self.power {
+            self.ch1.tick();
+            self.ch2.tick();
+            self.ch3.tick();
+            self.ch4.tick();
+        }
+        let level = self.mix();
+        let limit = self.sample_rate as usize * 4;
+        let (samples, high_pass) = (&mut self.samples, &mut self.high_pass);
+        self.resampler.tick(level, |sample| {
+            // A host that never drains the buffer only keeps the last second or so.
+            if samples.len() >= limit {
+                samples.drain(..limit / 2);
+            }
+            for sample in high_pass.filter(sample) {
+                samples.push(sample.clamp(-1.0, 1.0));
+            }
+        });
+    }
+    // Called at 512 Hz on a falling edge of DIV.
+    pub fn step_frame_sequencer(&mut self) {
+        if !self.power {
+            return;
+        }
+        if self.step & 1 == 0 {
+            self.ch1.clock_length();
+            self.ch2.clock_length();
+            self.ch3.clock_length();
+            self.ch4.clock_length();
+        }
+        if self.step == 2 || self.step == 6 {
+            self.ch1.clock_sweep();
+        }
+        if self.step == 7 {
+            self.ch1.clock_envelope();
+            self.ch2.clock_envelope();
+            self.ch4.clock_envelope();
+        }
+        self.step = (self.step + 1) & 0x07;
+    }
+    // Turning the APU off clears every register but wave RAM, and the DMG keeps its length
+    // counters.
+    fn set_power(&mut self, power: bool) {
+        if power && !self.power {
+            self.step = 0;
+        }
+        if !power && self.power {
+            self.ch1.power_off(!self.cgb);
+            self.ch2.power_off(!self.cgb);
+            self.ch3.power_off(!self.cgb);
+            self.ch4.power_off(!self.cgb);
+            self.nr50 = 0;
+            self.nr51 = 0;
+        }
+        self.power = power;
+    }
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xff10..=0xff14 => self.ch1.read(addr - 0xff10),
+            0xff15..=0xff19 => self.ch2.read(addr - 0xff15),
+            0xff1a..=0xff1e => self.ch3.read(addr - 0xff1a),
+            0xff1f..=0xff23 => self.ch4.read(addr - 0xff1f),
+            0xff24 => self.nr50,
+            0xff25 => self.nr51,
+            0xff26 => {
+                0x70 | (self.power as u8) << 7
+                    | (self.ch4.enabled() as u8) << 3
+                    | (self.ch3.enabled() as u8) << 2
+                    | (self.ch2.enabled() as u8) << 1
+                    | self.ch1.enabled() as u8
+            }
+            0xff30..=0xff3f => self.ch3.read_ram(addr - 0xff30),
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, addr: u16, val: u8) {
+        let first_half = self.first_half();
+        match addr {
+            0xff26 => self.set_power(val & 0x80 > 0),
+            0xff30..=0xff3f => self.ch3.write_ram(addr - 0xff30, val),
+            // Only the DMG length counters can be written while the APU is off.
+            0xff10..=0xff25 if !self.power => match addr {
+                _ if self.cgb => (),
+                0xff11 => self.ch1.load_length(val),
+                0xff16 => self.ch2.load_length(val),
+                0xff1b => self.ch3.load_length(val),
+                0xff20 => self.ch4.load_length(val),
+                _ => (),
+            },
+            0xff10..=0xff14 => self.ch1.write(addr - 0xff10, val, first_half),
+            0xff15..=0xff19 => self.ch2.write(addr - 0xff15, val, first_half),
+            0xff1a..=0xff1e => self.ch3.write(addr - 0xff1a, val, first_half),
+            0xff1f..=0xff23 => self.ch4.write(addr - 0xff1f, val, first_half),
+            0xff24 => self.nr50 = val,
+            0xff25 => self.nr51 = val,
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn powered() -> Apu {
+        let mut apu = Apu::new();
+        apu.write(0xff26, 0x80);
+        apu
+    }
+
+    #[test]
+    fn test_read_masks() {
+        let mut apu = powered();
+        let masks = [
+            0x80, 0x3f, 0x00, 0xff, 0xbf, 0xff, 0x3f, 0x00, 0xff, 0xbf, 0x7f, 0xff, 0x9f, 0xff,
+            0xbf, 0xff, 0xff, 0x00, 0x00, 0xbf, 0x00, 0x00,
+        ];
+        for (i, &mask) in masks.iter().enumerate() {
+            assert_eq!(apu.read(0xff10 + i as u16), mask, "{:#x}", 0xff10 + i);
+        }
+        for i in 0..masks.len() as u16 {
+            apu.write(0xff10 + i, 0xff);
+            assert_eq!(apu.read(0xff10 + i), 0xff, "{:#x}", 0xff10 + i);
+        }
+        apu.write(0xff26, 0x00);
+        for (i, &mask) in masks.iter().enumerate() {
+            assert_eq!(apu.read(0xff10 + i as u16), mask, "{:#x}", 0xff10 + i);
+        }
+        assert_eq!(apu.read(0xff26), 0x70);
+        assert_eq!(apu.read(0xff27), 0xff);
+    }
+
+    #[test]
+    fn test_power_off() {
+        let mut apu = powered();
+        apu.write(0xff30, 0x12);
+        apu.write(0xff12, 0xf0);
+        apu.write(0xff24, 0x77);
+        apu.write(0xff26, 0x00);
+        assert_eq!(apu.read(0xff30), 0x12);
+        assert_eq!(apu.read(0xff12), 0x00);
+        assert_eq!(apu.read(0xff24), 0x00);
+        apu.write(0xff12, 0xf0);
+        assert_eq!(apu.read(0xff12), 0x00);
+        assert_eq!(apu.read(0xff26), 0x70);
+
+        // The DMG keeps its length counters through a power cycle and lets them be written.
+        apu.write(0xff11, 0x3e);
+        apu.write(0xff26, 0x80);
+        apu.write(0xff12, 0xf0);
+        apu.write(0xff14, 0xc0);
+        assert_eq!(apu.read(0xff26), 0xf1);
+        apu.step_frame_sequencer();
+        apu.step_frame_sequencer();
+        assert_eq!(apu.read(0xff26), 0xf1);
+        apu.step_frame_sequencer();
+        assert_eq!(apu.read(0xff26), 0xf0);
+    }
+
+    #[test]
+    fn test_length() {
+        let mut apu = powered();
+        apu.write(0xff12, 0xf0);
+        apu.write(0xff11, 0x3c);
+        apu.write(0xff14, 0xc0);
+        assert_eq!(apu.read(0xff26) & 0x01, 0x01);
+        for _ in 0..6 {
+            apu.step_frame_sequencer();
+        }
+        assert_eq!(apu.read(0xff26) & 0x01, 0x01);
+        apu.step_frame_sequencer();
+        assert_eq!(apu.read(0xff26) & 0x01, 0x00);
+
+        // Enabling length when the next step doesn't clock it clocks it once straight away.
+        apu.step_frame_sequencer();
+        apu.step_frame_sequencer();
+        apu.write(0xff11, 0x3f);
+        apu.write(0xff14, 0x80);
+        assert_eq!(apu.read(0xff26) & 0x01, 0x01);
+        apu.write(0xff14, 0x40);
+        assert_eq!(apu.read(0xff26) & 0x01, 0x00);
+
+        // Triggering with an empty counter reloads it, one short in that same half.
+        apu.write(0xff14, 0xc0);
+        for _ in 0..125 {
+            apu.step_frame_sequencer();
+        }
+        assert_eq!(apu.read(0xff26) & 0x01, 0x01);
+        apu.step_frame_sequencer();
+        assert_eq!(apu.read(0xff26) & 0x01, 0x00);
+    }
+
+    #[test]
+    fn test_dac() {
+        let mut apu = powered();
+        apu.write(0xff14, 0x80);
+        assert_eq!(apu.read(0xff26), 0xf0);
+        apu.write(0xff12, 0x08);
+        apu.write(0xff14, 0x80);
+        assert_eq!(apu.read(0xff26), 0xf1);
+        apu.write(0xff12, 0x07);
+        assert_eq!(apu.read(0xff26), 0xf0);
+
+        apu.write(0xff1e, 0x80);
+        assert_eq!(apu.read(0xff26), 0xf0);
+        apu.write(0xff1a, 0x80);
+        apu.write(0xff1e, 0x80);
+        assert_eq!(apu.read(0xff26), 0xf4);
+        apu.write(0xff1a, 0x00);
+        assert_eq!(apu.read(0xff26), 0xf0);
+    }
+
+    #[test]
+    fn test_samples() {
+        let mut apu = powered();
+        apu.set_sample_rate(44100);
+        for _ in 0..APU_CLOCK {
+            apu.tick();
+        }
+        let samples = apu.drain_samples();
+        assert!((44099..=44100).contains(&(samples.len() / 2)));
+        assert!(samples.iter().all(|&sample| sample == 0.0));
+        assert!(apu.drain_samples().is_empty());
+    }
+
+    #[test]
+    fn test_mix() {
+        // Channel 2 at full volume with a 12.5% duty at 1 kHz, panned hard left.
+        let mut apu = powered();
+        apu.write(0xff24, 0x70);
+        apu.write(0xff25, 0x20);
+        apu.write(0xff16, 0x00);
+        apu.write(0xff17, 0xf0);
+        apu.write(0xff18, 0x7d);
+        apu.write(0xff19, 0x87);
+        for _ in 0..APU_CLOCK / 2 {
+            apu.tick();
+        }
+        let samples = apu.drain_samples();
+        assert!(samples.iter().skip(1).step_by(2).all(|&right| right == 0.0));
+        // The high-pass filter has removed the DC offset, leaving the pulses below zero.
+        let left: Vec<f32> = samples.iter().step_by(2).skip(19200).copied().collect();
+        let mean = left.iter().sum::<f32>() / left.len() as f32;
+        assert!(mean.abs() < 0.01, "{}", mean);
+        let min = left.iter().copied().fold(0.0, f32::min);
+        let max = left.iter().copied().fold(0.0, f32::max);
+        assert!((-0.5..-0.4).contains(&min), "{}", min);
+        assert!((0.05..0.15).contains(&max), "{}", max);
+    }
+
+    #[test]
+    fn test_rate_control() {
+        let mut apu = powered();
+        let count = |apu: &mut Apu| {
+            for _ in 0..APU_CLOCK / 8 {
+                apu.tick();
+            }
+            apu.drain_samples().len() / 2
+        };
+        assert_eq!(count(&mut apu), 6000);
+        // An empty host buffer speeds the output up by the full 0.5%, an overfull one slows
+        // it down.
+        apu.adjust_sample_rate(0, 4800);
+        assert!((6029..=6030).contains(&count(&mut apu)));
+        apu.adjust_sample_rate(4800 * 3, 4800);
+        assert!((5970..=5971).contains(&count(&mut apu)));
+        apu.adjust_sample_rate(4800, 4800);
+        assert!((5999..=6001).contains(&count(&mut apu)));
+    }
+}
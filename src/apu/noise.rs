@@ -0,0 +1,161 @@
+use super::{Envelope, Length};
+
+// NR43 divisor codes in M-cycles, before the clock shift.
+const DIVISORS: [u32; 8] = [2, 4, 8, 12, 16, 20, 24, 28];
+
+pub struct Noise {
+    length: Length,
+    envelope: Envelope,
+    reg: u8,
+    timer: u32,
+    lfsr: u16,
+    enabled: bool,
+}
+impl Noise {
+    pub fn new() -> Self {
+        Self {
+            length: Length::new(64),
+            envelope: Envelope::default(),
+            reg: 0,
+            timer: 0,
+            lfsr: 0x7fff,
+            enabled: false,
+        }
+    }
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+    pub fn power_off(&mut self, keep_length: bool) {
+        let length = self.length.counter;
+        *self = Self::new();
+        if keep_length {
+            self.length.counter = length;
+        }
+    }
+    fn period(&self) -> u32 {
+        DIVISORS[(self.reg & 0x07) as usize] << (self.reg >> 4)
+    }
+    pub fn output(&self) -> Option<u8> {
+        if !self.envelope.dac_enabled() {
+            return None;
+        }
+        Some(if self.enabled && self.lfsr & 1 == 0 {
+            self.envelope.volume
+        } else {
+            0
+        })
+    }
+    // Bit 0 XOR bit 1 shifts in at bit 14, and at bit 6 as well in 7-bit mode. Shifts of 14
+    // and 15 stop the LFSR.
+    pub fn tick(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return;
+        }
+        self.timer = self.period();
+        if self.reg >> 4 >= 14 {
+            return;
+        }
+        let bit = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+        self.lfsr = (self.lfsr >> 1) | bit << 14;
+        if self.reg & 0x08 > 0 {
+            self.lfsr = (self.lfsr & !0x40) | bit << 6;
+        }
+    }
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.timer = self.period();
+        self.lfsr = 0x7fff;
+        self.envelope.trigger();
+    }
+    pub fn load_length(&mut self, val: u8) {
+        self.length.load(val);
+    }
+    pub fn read(&self, reg: u16) -> u8 {
+        match reg {
+            2 => self.envelope.reg,
+            3 => self.reg,
+            4 => 0xbf | (self.length.enabled as u8) << 6,
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, reg: u16, val: u8, first_half: bool) {
+        match reg {
+            0 => (),
+            1 => self.length.load(val),
+            2 => {
+                self.envelope.reg = val;
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.reg = val,
+            _ => {
+                let trigger = val & 0x80 > 0;
+                if self.length.write(val & 0x40 > 0, trigger, first_half) {
+                    self.enabled = false;
+                }
+                if trigger {
+                    self.trigger();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(nr43: u8, steps: usize) -> Vec<u8> {
+        let mut noise = Noise::new();
+        noise.write(2, 0xf0, false);
+        noise.write(3, nr43, false);
+        noise.write(4, 0x80, false);
+        let period = noise.period();
+        (0..steps)
+            .map(|_| {
+                for _ in 0..period {
+                    noise.tick();
+                }
+                noise.output().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lfsr() {
+        let wide = run(0x00, 0x7fff * 2);
+        assert_eq!(wide[..14], [0; 14]);
+        assert_eq!(wide[..0x7fff], wide[0x7fff..]);
+        assert_eq!(wide.iter().filter(|&&out| out > 0).count(), 0x3fff * 2);
+
+        let narrow = run(0x08, 0x7f * 2);
+        assert_eq!(narrow[..0x7f], narrow[0x7f..]);
+        assert_eq!(narrow.iter().filter(|&&out| out > 0).count(), 0x3f * 2);
+    }
+
+    #[test]
+    fn test_period() {
+        let mut noise = Noise::new();
+        noise.write(3, 0x00, false);
+        assert_eq!(noise.period(), 2);
+        noise.write(3, 0x27, false);
+        assert_eq!(noise.period(), 112);
+        noise.write(3, 0xe0, false);
+        noise.write(2, 0xf0, false);
+        noise.write(4, 0x80, false);
+        for _ in 0..0x100000 {
+            noise.tick();
+        }
+        assert_eq!(noise.lfsr, 0x7fff);
+    }
+}
@@ -0,0 +1,249 @@
+use super::{Envelope, Length};
+
+// One byte per duty setting, played from the top bit down.
+const DUTY: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+
+#[derive(Default)]
+struct Sweep {
+    reg: u8,
+    timer: u8,
+    enabled: bool,
+    shadow: u16,
+    // A subtraction has been calculated since the trigger. Clearing the negate bit after
+    // that turns the channel off.
+    negated: bool,
+}
+impl Sweep {
+    fn reload(&mut self) {
+        self.timer = match (self.reg >> 4) & 0x07 {
+            0 => 8,
+            period => period,
+        };
+    }
+    fn calc(&mut self) -> u16 {
+        let delta = self.shadow >> (self.reg & 0x07);
+        if self.reg & 0x08 > 0 {
+            self.negated = true;
+            self.shadow - delta
+        } else {
+            self.shadow + delta
+        }
+    }
+}
+
+pub struct Pulse {
+    sweep: Option<Sweep>,
+    duty: u8,
+    length: Length,
+    envelope: Envelope,
+    freq: u16,
+    timer: u16,
+    position: u8,
+    enabled: bool,
+}
+impl Pulse {
+    pub fn new(sweep: bool) -> Self {
+        Self {
+            sweep: sweep.then(Sweep::default),
+            duty: 0,
+            length: Length::new(64),
+            envelope: Envelope::default(),
+            freq: 0,
+            timer: 0,
+            position: 0,
+            enabled: false,
+        }
+    }
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+    pub fn power_off(&mut self, keep_length: bool) {
+        let length = self.length.counter;
+        *self = Self::new(self.sweep.is_some());
+        if keep_length {
+            self.length.counter = length;
+        }
+    }
+    pub fn output(&self) -> Option<u8> {
+        if !self.envelope.dac_enabled() {
+            return None;
+        }
+        let high = (DUTY[self.duty as usize] >> (7 - self.position)) & 1 > 0;
+        Some(if self.enabled && high {
+            self.envelope.volume
+        } else {
+            0
+        })
+    }
+    // The frequency timer counts down M-cycles from 2048 - frequency.
+    pub fn tick(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = 2048 - self.freq;
+            self.position = (self.position + 1) & 0x07;
+        }
+    }
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+    pub fn clock_sweep(&mut self) {
+        let Some(sweep) = &mut self.sweep else {
+            return;
+        };
+        sweep.timer = sweep.timer.saturating_sub(1);
+        if sweep.timer > 0 {
+            return;
+        }
+        sweep.reload();
+        if !sweep.enabled || sweep.reg & 0x70 == 0 {
+            return;
+        }
+        let freq = sweep.calc();
+        if freq > 0x7ff {
+            self.enabled = false;
+        } else if sweep.reg & 0x07 > 0 {
+            sweep.shadow = freq;
+            self.freq = freq;
+            if sweep.calc() > 0x7ff {
+                self.enabled = false;
+            }
+        }
+    }
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.timer = 2048 - self.freq;
+        self.envelope.trigger();
+        if let Some(sweep) = &mut self.sweep {
+            sweep.shadow = self.freq;
+            sweep.negated = false;
+            sweep.reload();
+            sweep.enabled = sweep.reg & 0x77 > 0;
+            if sweep.reg & 0x07 > 0 && sweep.calc() > 0x7ff {
+                self.enabled = false;
+            }
+        }
+    }
+    pub fn load_length(&mut self, val: u8) {
+        self.length.load(val);
+    }
+    pub fn read(&self, reg: u16) -> u8 {
+        match reg {
+            0 => self.sweep.as_ref().map_or(0xff, |sweep| 0x80 | sweep.reg),
+            1 => 0x3f | self.duty << 6,
+            2 => self.envelope.reg,
+            4 => 0xbf | (self.length.enabled as u8) << 6,
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, reg: u16, val: u8, first_half: bool) {
+        match reg {
+            0 => {
+                if let Some(sweep) = &mut self.sweep {
+                    sweep.reg = val & 0x7f;
+                    if sweep.negated && val & 0x08 == 0 {
+                        self.enabled = false;
+                    }
+                }
+            }
+            1 => {
+                self.duty = val >> 6;
+                self.length.load(val);
+            }
+            2 => {
+                self.envelope.reg = val;
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.freq = (self.freq & 0x700) | val as u16,
+            _ => {
+                self.freq = (self.freq & 0x0ff) | ((val & 0x07) as u16) << 8;
+                let trigger = val & 0x80 > 0;
+                if self.length.write(val & 0x40 > 0, trigger, first_half) {
+                    self.enabled = false;
+                }
+                if trigger {
+                    self.trigger();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(pulse: &mut Pulse, nr10: u8, freq: u16) {
+        pulse.write(0, nr10, false);
+        pulse.write(2, 0xf0, false);
+        pulse.write(3, freq as u8, false);
+        pulse.write(4, 0x80 | (freq >> 8) as u8, false);
+    }
+
+    #[test]
+    fn test_duty() {
+        let mut pulse = Pulse::new(false);
+        pulse.write(1, 0x40, false);
+        trigger(&mut pulse, 0, 0x7fe);
+        let mut wave = vec![];
+        for _ in 0..16 {
+            pulse.tick();
+            wave.push(pulse.output().unwrap());
+        }
+        assert_eq!(wave, [15, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 15, 15, 15]);
+    }
+
+    #[test]
+    fn test_envelope() {
+        let mut pulse = Pulse::new(false);
+        pulse.write(2, 0x32, false);
+        pulse.write(4, 0x80, false);
+        assert_eq!(pulse.envelope.volume, 3);
+        pulse.clock_envelope();
+        assert_eq!(pulse.envelope.volume, 3);
+        pulse.clock_envelope();
+        assert_eq!(pulse.envelope.volume, 2);
+        for _ in 0..10 {
+            pulse.clock_envelope();
+        }
+        assert_eq!(pulse.envelope.volume, 0);
+        pulse.write(2, 0xe9, false);
+        pulse.write(4, 0x80, false);
+        pulse.clock_envelope();
+        assert_eq!(pulse.envelope.volume, 15);
+    }
+
+    #[test]
+    fn test_sweep() {
+        let mut pulse = Pulse::new(true);
+        trigger(&mut pulse, 0x12, 0x400);
+        pulse.clock_sweep();
+        assert_eq!(pulse.freq, 0x500);
+        pulse.clock_sweep();
+        assert_eq!(pulse.freq, 0x640);
+        assert!(pulse.enabled());
+        // The second calculation overflows and turns the channel off after the update.
+        pulse.clock_sweep();
+        assert_eq!(pulse.freq, 0x7d0);
+        assert!(!pulse.enabled());
+
+        // Overflow is checked on trigger when the shift is non-zero.
+        trigger(&mut pulse, 0x11, 0x7ff);
+        assert!(!pulse.enabled());
+
+        trigger(&mut pulse, 0x2a, 0x400);
+        pulse.clock_sweep();
+        assert_eq!(pulse.freq, 0x400);
+        pulse.clock_sweep();
+        assert_eq!(pulse.freq, 0x300);
+        assert!(pulse.enabled());
+        pulse.write(0, 0x22, false);
+        assert!(!pulse.enabled());
+    }
+}
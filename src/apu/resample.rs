@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use super::APU_CLOCK;
+
+// Kernel width in output samples, and fractional positions it is precomputed for.
+const TAPS: usize = 32;
+const PHASES: usize = 64;
+// Cutoff as a fraction of the output rate, leaving room for the window's transition band
+// below Nyquist.
+const CUTOFF: f64 = 0.42;
+
+// Blackman-windowed sinc impulses, one row per phase, each summing to 1 so that integrating
+// them gives a step of exactly the right height.
+fn kernel() -> Box<[[f32; TAPS]; PHASES + 1]> {
+    let mut kernel = Box::new([[0.0; TAPS]; PHASES + 1]);
+    for (phase, taps) in kernel.iter_mut().enumerate() {
+        let frac = phase as f64 / PHASES as f64;
+        let mut row = [0.0; TAPS];
+        for (i, tap) in row.iter_mut().enumerate() {
+            let x = i as f64 - (TAPS / 2) as f64 + 1.0 - frac;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (2.0 * PI * CUTOFF * x).sin() / (2.0 * PI * CUTOFF * x)
+            };
+            let w = 2.0 * PI * x / TAPS as f64;
+            *tap = sinc * (0.42 + 0.5 * w.cos() + 0.08 * (2.0 * w).cos());
+        }
+        let sum: f64 = row.iter().sum();
+        for (tap, val) in taps.iter_mut().zip(row) {
+            *tap = (val / sum) as f32;
+        }
+    }
+    kernel
+}
+
+// Band-limited step synthesis. The mixer output only moves in steps, so each change is added
+// as a band-limited impulse at its exact fractional output position and the impulses are
+// integrated back into steps. Nothing above the output Nyquist rate survives to alias.
+pub struct Resampler {
+    kernel: Box<[[f32; TAPS]; PHASES + 1]>,
+    // Output samples per APU cycle.
+    step: f64,
+    // Position within the next output sample.
+    time: f64,
+    level: [f32; 2],
+    deltas: VecDeque<[f32; 2]>,
+    sum: [f32; 2],
+}
+impl Resampler {
+    pub fn new(rate: f64) -> Self {
+        Self {
+            kernel: kernel(),
+            step: rate / APU_CLOCK as f64,
+            time: 0.0,
+            level: [0.0; 2],
+            deltas: VecDeque::from(vec![[0.0; 2]; TAPS]),
+            sum: [0.0; 2],
+        }
+    }
+    pub fn set_rate(&mut self, rate: f64) {
+        self.step = rate / APU_CLOCK as f64;
+    }
+    // Takes the mix for one APU cycle and passes on each output sample as it completes.
+    pub fn tick(&mut self, level: [f32; 2], mut output: impl FnMut([f32; 2])) {
+        if level != self.level {
+            // Interpolating between the two nearest phases keeps the timing error well below
+            // what the phase count alone would give.
+            let phase = self.time * PHASES as f64;
+            let index = (phase as usize).min(PHASES - 1);
+            let frac = (phase - index as f64) as f32;
+            let taps = self.kernel[index].iter().zip(&self.kernel[index + 1]);
+            for (delta, (&a, &b)) in self.deltas.iter_mut().zip(taps) {
+                let tap = a + (b - a) * frac;
+                delta[0] += (level[0] - self.level[0]) * tap;
+                delta[1] += (level[1] - self.level[1]) * tap;
+            }
+            self.level = level;
+        }
+        self.time += self.step;
+        while self.time >= 1.0 {
+            self.time -= 1.0;
+            let delta = self.deltas.pop_front().unwrap_or_default();
+            self.deltas.push_back([0.0; 2]);
+            self.sum[0] += delta[0];
+            self.sum[1] += delta[1];
+            output(self.sum);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(resampler: &mut Resampler, levels: impl Iterator<Item = f32>) -> Vec<f32> {
+        let mut samples = vec![];
+        for level in levels {
+            resampler.tick([level, -level], |sample| {
+                assert_eq!(sample[0], -sample[1]);
+                samples.push(sample[0]);
+            });
+        }
+        samples
+    }
+
+    #[test]
+    fn test_kernel() {
+        for taps in kernel().iter() {
+            let sum: f32 = taps.iter().sum();
+            assert!((sum - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_step() {
+        let mut resampler = Resampler::new(48000.0);
+        let samples = run(&mut resampler, (0..APU_CLOCK / 64).map(|_| 0.5));
+        assert_eq!(samples.len(), 750);
+        assert!(
+            samples[..TAPS / 2 - 4]
+                .iter()
+                .all(|&sample| sample.abs() < 0.02)
+        );
+        assert!(
+            samples[TAPS..]
+                .iter()
+                .all(|&sample| (sample - 0.5).abs() < 1e-5)
+        );
+    }
+
+    #[test]
+    fn test_alias() {
+        // A tone far above Nyquist averages out rather than folding down as point sampling
+        // would.
+        let mut resampler = Resampler::new(44100.0);
+        let samples = run(
+            &mut resampler,
+            (0..APU_CLOCK / 10).map(|i| ((i / 3) & 1) as f32),
+        );
+        assert!(
+            samples[TAPS..]
+                .iter()
+                .all(|&sample| (sample - 0.5).abs() < 0.001)
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use super::Length;
+
+// NR32 output level as a right shift of the 4-bit sample.
+const LEVEL_SHIFT: [u8; 4] = [4, 0, 1, 2];
+
+pub struct Wave {
+    dac: bool,
+    length: Length,
+    level: u8,
+    freq: u16,
+    timer: u16,
+    position: u8,
+    // The sample last read from wave RAM. A trigger doesn't refresh it, so the old sample plays
+    // until the first step.
+    sample: u8,
+    ram: [u8; 0x10],
+    enabled: bool,
+}
+impl Wave {
+    pub fn new() -> Self {
+        Self {
+            dac: false,
+            length: Length::new(256),
+            level: 0,
+            freq: 0,
+            timer: 0,
+            position: 0,
+            sample: 0,
+            ram: [0; 0x10],
+            enabled: false,
+        }
+    }
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+    pub fn power_off(&mut self, keep_length: bool) {
+        let (ram, length) = (self.ram, self.length.counter);
+        *self = Self::new();
+        self.ram = ram;
+        if keep_length {
+            self.length.counter = length;
+        }
+    }
+    pub fn output(&self) -> Option<u8> {
+        if !self.dac {
+            return None;
+        }
+        Some(if self.enabled {
+            self.sample >> LEVEL_SHIFT[self.level as usize]
+        } else {
+            0
+        })
+    }
+    // The frequency timer counts down in 2 T-cycle units from 2048 - frequency, so it can
+    // step twice per M-cycle.
+    pub fn tick(&mut self) {
+        for _ in 0..2 {
+            self.timer = self.timer.saturating_sub(1);
+            if self.timer == 0 {
+                self.timer = 2048 - self.freq;
+                self.position = (self.position + 1) & 0x1f;
+                let byte = self.ram[self.position as usize / 2];
+                self.sample = if self.position & 1 == 0 {
+                    byte >> 4
+                } else {
+                    byte & 0x0f
+                };
+            }
+        }
+    }
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+    fn trigger(&mut self) {
+        self.enabled = self.dac;
+        self.timer = 2048 - self.freq;
+        self.position = 0;
+    }
+    pub fn load_length(&mut self, val: u8) {
+        self.length.load(val);
+    }
+    pub fn read(&self, reg: u16) -> u8 {
+        match reg {
+            0 => 0x7f | (self.dac as u8) << 7,
+            2 => 0x9f | self.level << 5,
+            4 => 0xbf | (self.length.enabled as u8) << 6,
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, reg: u16, val: u8, first_half: bool) {
+        match reg {
+            0 => {
+                self.dac = val & 0x80 > 0;
+                if !self.dac {
+                    self.enabled = false;
+                }
+            }
+            1 => self.length.load(val),
+            2 => self.level = (val >> 5) & 0x03,
+            3 => self.freq = (self.freq & 0x700) | val as u16,
+            _ => {
+                self.freq = (self.freq & 0x0ff) | ((val & 0x07) as u16) << 8;
+                let trigger = val & 0x80 > 0;
+                if self.length.write(val & 0x40 > 0, trigger, first_half) {
+                    self.enabled = false;
+                }
+                if trigger {
+                    self.trigger();
+                }
+            }
+        }
+    }
+    // While the channel plays, wave RAM accesses land on the byte it is reading.
+    pub fn read_ram(&self, offset: u16) -> u8 {
+        if self.enabled {
+            self.ram[self.position as usize / 2]
+        } else {
+            self.ram[offset as usize]
+        }
+    }
+    pub fn write_ram(&mut self, offset: u16, val: u8) {
+        if self.enabled {
+            self.ram[self.position as usize / 2] = val;
+        } else {
+            self.ram[offset as usize] = val;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback() {
+        let mut wave = Wave::new();
+        for i in 0..0x10 {
+            wave.write_ram(i, ((i as u8) << 4) | (0x0f - i as u8));
+        }
+        wave.write(0, 0x80, false);
+        wave.write(2, 0x20, false);
+        wave.write(3, 0xfe, false);
+        wave.write(4, 0x87, false);
+        assert_eq!(wave.output(), Some(0));
+        let mut samples = vec![];
+        for _ in 0..4 {
+            wave.tick();
+            samples.push(wave.output().unwrap());
+        }
+        assert_eq!(samples, [15, 1, 14, 2]);
+        assert_eq!(wave.read_ram(0x00), 0x2d);
+
+        wave.write(2, 0x60, false);
+        wave.tick();
+        assert_eq!(wave.output(), Some(3));
+        wave.tick();
+        assert_eq!(wave.output(), Some(0));
+    }
+
+    #[test]
+    fn test_ram() {
+        let mut wave = Wave::new();
+        wave.write_ram(0x05, 0x42);
+        assert_eq!(wave.read_ram(0x05), 0x42);
+        wave.power_off(false);
+        assert_eq!(wave.read_ram(0x05), 0x42);
+    }
+}
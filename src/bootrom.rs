@@ -6,13 +6,35 @@ impl Bootrom {
     pub fn new(rom: Box<[u8]>) -> Self {
         Self { rom, active: true }
     }
+    // The CGB boot ROM is 2304 bytes and also maps 0x0200-0x08ff, around the cartridge header.
+    pub fn is_cgb(&self) -> bool {
+        self.rom.len() >= 0x900
+    }
     pub fn is_active(&self) -> bool {
         self.active
     }
     pub fn write(&mut self, _: u16, val: u8) {
         self.active &= val == 0;
     }
+    // A truncated image reads as open bus past its end.
     pub fn read(&self, addr: u16) -> u8 {
-        self.rom[addr as usize]
+        self.rom.get(addr as usize).copied().unwrap_or(0xff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_size() {
+        let bootrom = Bootrom::new(vec![0x42; 0x80].into_boxed_slice());
+        assert!(!bootrom.is_cgb());
+        assert_eq!(bootrom.read(0x7f), 0x42);
+        assert_eq!(bootrom.read(0x80), 0xff);
+        let bootrom = Bootrom::new(vec![0x42; 0x200].into_boxed_slice());
+        assert!(!bootrom.is_cgb());
+        let bootrom = Bootrom::new(vec![0x42; 0x900].into_boxed_slice());
+        assert!(bootrom.is_cgb());
+        assert_eq!(bootrom.read(0x8ff), 0x42);
     }
 }
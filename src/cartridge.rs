@@ -0,0 +1,559 @@
+mod camera;
+mod huc1;
+mod huc3;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod mbc6;
+mod mbc7;
+mod mmm01;
+
+use std::{fmt, time};
+
+pub use camera::{CAMERA_HEIGHT, CAMERA_WIDTH, ImageSource};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    TooSmall(usize),
+    UnknownType(u8),
+    UnsupportedType(u8),
+    UnknownRomSize(u8),
+    UnknownRamSize(u8),
+    Truncated { expected: usize, actual: usize },
+    HeaderChecksum { expected: u8, actual: u8 },
+    GlobalChecksum { expected: u16, actual: u16 },
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::TooSmall(len) => write!(f, "rom image is too small for a header: {} bytes", len),
+            Error::UnknownType(t) => write!(f, "unknown cartridge type {:02x}", t),
+            Error::UnsupportedType(t) => write!(f, "unsupported cartridge type {:02x}", t),
+            Error::UnknownRomSize(code) => write!(f, "unknown rom size code {:02x}", code),
+            Error::UnknownRamSize(code) => write!(f, "unknown ram size code {:02x}", code),
+            Error::Truncated { expected, actual } => write!(
+                f,
+                "rom image is truncated: expected {} bytes, got {}",
+                expected, actual
+            ),
+            Error::HeaderChecksum { expected, actual } => write!(
+                f,
+                "header checksum mismatch: expected {:02x}, got {:02x}",
+                expected, actual
+            ),
+            Error::GlobalChecksum { expected, actual } => write!(
+                f,
+                "global checksum mismatch: expected {:04x}, got {:04x}",
+                expected, actual
+            ),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Licensee {
+    Old(u8),
+    New([u8; 2]),
+}
+
+#[derive(Clone, Debug)]
+pub struct Header {
+    pub title: String,
+    pub cgb_flag: u8,
+    pub sgb_flag: u8,
+    pub cartridge_type: u8,
+    pub rom_size: u8,
+    pub ram_size: u8,
+    pub licensee: Licensee,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+impl Header {
+    pub fn parse(rom: &[u8]) -> Result<Self, Error> {
+        if rom.len() < 0x150 {
+            return Err(Error::TooSmall(rom.len()));
+        }
+        let cgb_flag = rom[0x143];
+        let title_end = if cgb_flag & 0x80 > 0 { 0x143 } else { 0x144 };
+        let title = rom[0x134..title_end]
+            .iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| {
+                if c.is_ascii_graphic() || c == b' ' {
+                    c as char
+                } else {
+                    '?'
+                }
+            })
+            .collect();
+        let licensee = match rom[0x14b] {
+            0x33 => Licensee::New([rom[0x144], rom[0x145]]),
+            code => Licensee::Old(code),
+        };
+        Ok(Self {
+            title,
+            cgb_flag,
+            sgb_flag: rom[0x146],
+            cartridge_type: rom[0x147],
+            rom_size: rom[0x148],
+            ram_size: rom[0x149],
+            licensee,
+            version: rom[0x14c],
+            header_checksum: rom[0x14d],
+            global_checksum: u16::from_be_bytes([rom[0x14e], rom[0x14f]]),
+        })
+    }
+    pub fn supports_cgb(&self) -> bool {
+        self.cgb_flag & 0x80 > 0
+    }
+    pub fn cgb_only(&self) -> bool {
+        self.cgb_flag == 0xc0
+    }
+    pub fn supports_sgb(&self) -> bool {
+        self.sgb_flag == 0x03
+    }
+    pub fn has_battery(&self) -> bool {
+        matches!(
+            self.cartridge_type,
+            0x03 | 0x06
+                | 0x09
+                | 0x0d
+                | 0x0f
+                | 0x10
+                | 0x13
+                | 0x1b
+                | 0x1e
+                | 0x20
+                | 0x22
+                | 0xfc
+                | 0xfe
+                | 0xff
+        )
+    }
+    pub fn rom_bytes(&self) -> Result<usize, Error> {
+        match self.rom_size {
+            0x00..=0x08 => Ok(0x8000 << self.rom_size),
+            code => Err(Error::UnknownRomSize(code)),
+        }
+    }
+    pub fn ram_bytes(&self) -> Result<usize, Error> {
+        match self.ram_size {
+            0x00 => Ok(0),
+            0x01 => Ok(0x800),
+            0x02 => Ok(0x2000),
+            0x03 => Ok(0x8000),
+            0x04 => Ok(0x20000),
+            0x05 => Ok(0x10000),
+            code => Err(Error::UnknownRamSize(code)),
+        }
+    }
+}
+
+fn header_checksum(rom: &[u8]) -> u8 {
+    rom[0x134..=0x14c]
+        .iter()
+        .fold(0u8, |x, &b| x.wrapping_sub(b).wrapping_sub(1))
+}
+
+fn global_checksum(rom: &[u8], offset: usize) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|&(i, _)| i != offset + 0x14e && i != offset + 0x14f)
+        .fold(0u16, |x, (_, &b)| x.wrapping_add(b as u16))
+}
+
+const NINTENDO_LOGO: [u8; 0x30] = [
+    0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0c, 0x00, 0x0d,
+    0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e, 0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99,
+    0xbb, 0xbb, 0x67, 0x63, 0x6e, 0x0e, 0xec, 0xcc, 0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e,
+];
+
+const MCYCLES_PER_SECOND: u32 = 0x100000;
+
+fn read_rom(rom: &[u8], bank: usize, addr: u16) -> u8 {
+    rom[(bank * 0x4000 + (addr & 0x3fff) as usize) % rom.len()]
+}
+
+fn read_ram(ram: &[u8], bank: usize, addr: u16) -> u8 {
+    if ram.is_empty() {
+        return 0xff;
+    }
+    ram[(bank * 0x2000 + (addr & 0x1fff) as usize) % ram.len()]
+}
+
+fn write_ram(ram: &mut [u8], bank: usize, addr: u16, val: u8) {
+    if ram.is_empty() {
+        return;
+    }
+    let len = ram.len();
+    ram[(bank * 0x2000 + (addr & 0x1fff) as usize) % len] = val;
+}
+
+enum Mbc {
+    RomOnly,
+    Mbc1(mbc1::Mbc1),
+    Mbc2(mbc2::Mbc2),
+    Mbc3(mbc3::Mbc3),
+    Mbc5(mbc5::Mbc5),
+    Mbc6(mbc6::Mbc6),
+    Mbc7(mbc7::Mbc7),
+    Mmm01(mmm01::Mmm01),
+    HuC1(huc1::HuC1),
+    HuC3(huc3::HuC3),
+    Camera(camera::Camera),
+}
+
+pub(crate) fn unix_time() -> u64 {
+    time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub struct Cartridge {
+    header: Header,
+    rom: Box<[u8]>,
+    ram: Box<[u8]>,
+    mbc: Mbc,
+}
+impl Cartridge {
+    pub fn new(rom: Box<[u8]>) -> Result<Self, Error> {
+        let offset = mmm01::menu_offset(&rom).unwrap_or(0);
+        let header = Header::parse(&rom[offset..])?;
+        let actual = header_checksum(&rom[offset..]);
+        if actual != header.header_checksum {
+            return Err(Error::HeaderChecksum {
+                expected: header.header_checksum,
+                actual,
+            });
+        }
+        let rom_bytes = header.rom_bytes()?;
+        if rom.len() < rom_bytes {
+            return Err(Error::Truncated {
+                expected: rom_bytes,
+                actual: rom.len(),
+            });
+        }
+        let ram_bytes = match header.cartridge_type {
+            0x05 | 0x06 => 0x200,
+            0x22 => 0x100,
+            _ => header.ram_bytes()?,
+        };
+        let ram = vec![0; ram_bytes].into_boxed_slice();
+        let mbc = match header.cartridge_type {
+            0x00 | 0x08 | 0x09 => Mbc::RomOnly,
+            0x01..=0x03 => Mbc::Mbc1(mbc1::Mbc1::new(&rom)),
+            0x05 | 0x06 => Mbc::Mbc2(mbc2::Mbc2::new()),
+            0x0b..=0x0d => Mbc::Mmm01(mmm01::Mmm01::new()),
+            0x0f | 0x10 => Mbc::Mbc3(mbc3::Mbc3::new(&rom, &ram, true)),
+            0x11..=0x13 => Mbc::Mbc3(mbc3::Mbc3::new(&rom, &ram, false)),
+            0x19..=0x1b => Mbc::Mbc5(mbc5::Mbc5::new(false)),
+            0x1c..=0x1e => Mbc::Mbc5(mbc5::Mbc5::new(true)),
+            0x20 => Mbc::Mbc6(mbc6::Mbc6::new()),
+            0x22 => Mbc::Mbc7(mbc7::Mbc7::new()),
+            0xfc => Mbc::Camera(camera::Camera::new()),
+            0xfd => return Err(Error::UnsupportedType(0xfd)),
+            0xfe => Mbc::HuC3(huc3::HuC3::new()),
+            0xff => Mbc::HuC1(huc1::HuC1::new()),
+            t => return Err(Error::UnknownType(t)),
+        };
+        Ok(Self {
+            header,
+            rom,
+            ram,
+            mbc,
+        })
+    }
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+    // The hardware never checks the global checksum, and patched or multicart images often
+    // get it wrong, so a mismatch is reported here rather than refusing the cartridge.
+    pub fn verify(&self) -> Result<(), Error> {
+        let offset = mmm01::menu_offset(&self.rom).unwrap_or(0);
+        let actual = global_checksum(&self.rom, offset);
+        if actual != self.header.global_checksum {
+            return Err(Error::GlobalChecksum {
+                expected: self.header.global_checksum,
+                actual,
+            });
+        }
+        Ok(())
+    }
+    pub fn tick(&mut self) {
+        match &mut self.mbc {
+            Mbc::Mbc3(mbc) => mbc.tick(),
+            Mbc::HuC3(mbc) => mbc.tick(),
+            Mbc::Camera(mbc) => mbc.tick(&mut self.ram),
+            _ => (),
+        }
+    }
+    pub fn ir_led(&self) -> bool {
+        match &self.mbc {
+            Mbc::HuC1(mbc) => mbc.ir_led(),
+            Mbc::HuC3(mbc) => mbc.ir_led(),
+            _ => false,
+        }
+    }
+    pub fn set_ir_light(&mut self, light: bool) {
+        match &mut self.mbc {
+            Mbc::HuC1(mbc) => mbc.set_ir_light(light),
+            Mbc::HuC3(mbc) => mbc.set_ir_light(light),
+            _ => (),
+        }
+    }
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        if let Mbc::Mbc7(mbc) = &mut self.mbc {
+            mbc.set_tilt(x, y);
+        }
+    }
+    pub fn set_camera_source(&mut self, source: Box<dyn ImageSource>) {
+        if let Mbc::Camera(mbc) = &mut self.mbc {
+            mbc.set_source(source);
+        }
+    }
+    pub fn rumble(&self) -> bool {
+        match &self.mbc {
+            Mbc::Mbc5(mbc) => mbc.rumble(),
+            _ => false,
+        }
+    }
+    pub fn save_data(&self) -> Vec<u8> {
+        self.save_data_at(unix_time())
+    }
+    // `now` is only used for the timestamp in clock footers.
+    pub fn save_data_at(&self, now: u64) -> Vec<u8> {
+        let mut data = self.ram.to_vec();
+        match &self.mbc {
+            Mbc::Mbc3(mbc) => {
+                if let Some(rtc) = mbc.rtc() {
+                    data.extend(rtc.save(now));
+                }
+            }
+            Mbc::Mbc6(mbc) => data.extend_from_slice(mbc.flash()),
+            Mbc::HuC3(mbc) => data.extend(mbc.save(now)),
+            _ => (),
+        }
+        data
+    }
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        let len = data.len().min(self.ram.len());
+        self.ram[..len].copy_from_slice(&data[..len]);
+        let footer = &data[len..];
+        match &mut self.mbc {
+            Mbc::Mbc3(mbc) => {
+                if let Some(rtc) = mbc.rtc_mut() {
+                    rtc.load(footer, unix_time());
+                }
+            }
+            Mbc::Mbc6(mbc) => mbc.load_flash(footer),
+            Mbc::HuC3(mbc) => mbc.load(footer, unix_time()),
+            _ => (),
+        }
+    }
+    pub fn read(&self, addr: u16) -> u8 {
+        match &self.mbc {
+            Mbc::RomOnly => match addr {
+                0x0000..=0x7fff => self.rom[addr as usize],
+                0xa000..=0xbfff => self
+                    .ram
+                    .get((addr - 0xa000) as usize)
+                    .copied()
+                    .unwrap_or(0xff),
+                _ => 0xff,
+            },
+            Mbc::Mbc1(mbc) => mbc.read(&self.rom, &self.ram, addr),
+            Mbc::Mbc2(mbc) => mbc.read(&self.rom, &self.ram, addr),
+            Mbc::Mbc3(mbc) => mbc.read(&self.rom, &self.ram, addr),
+            Mbc::Mbc5(mbc) => mbc.read(&self.rom, &self.ram, addr),
+            Mbc::Mbc6(mbc) => mbc.read(&self.rom, &self.ram, addr),
+            Mbc::Mbc7(mbc) => mbc.read(&self.rom, addr),
+            Mbc::Mmm01(mbc) => mbc.read(&self.rom, &self.ram, addr),
+            Mbc::HuC1(mbc) => mbc.read(&self.rom, &self.ram, addr),
+            Mbc::HuC3(mbc) => mbc.read(&self.rom, &self.ram, addr),
+            Mbc::Camera(mbc) => mbc.read(&self.rom, &self.ram, addr),
+        }
+    }
+    pub fn write(&mut self, addr: u16, val: u8) {
+        match &mut self.mbc {
+            Mbc::RomOnly => {
+                if let 0xa000..=0xbfff = addr
+                    && let Some(b) = self.ram.get_mut((addr - 0xa000) as usize)
+                {
+                    *b = val;
+                }
+            }
+            Mbc::Mbc1(mbc) => mbc.write(&mut self.ram, addr, val),
+            Mbc::Mbc2(mbc) => mbc.write(&mut self.ram, addr, val),
+            Mbc::Mbc3(mbc) => mbc.write(&mut self.ram, addr, val),
+            Mbc::Mbc5(mbc) => mbc.write(&mut self.ram, addr, val),
+            Mbc::Mbc6(mbc) => mbc.write(&mut self.ram, addr, val),
+            Mbc::Mbc7(mbc) => mbc.write(&mut self.ram, addr, val),
+            Mbc::Mmm01(mbc) => mbc.write(&mut self.ram, addr, val),
+            Mbc::HuC1(mbc) => mbc.write(&mut self.ram, addr, val),
+            Mbc::HuC3(mbc) => mbc.write(&mut self.ram, addr, val),
+            Mbc::Camera(mbc) => mbc.write(&mut self.ram, addr, val),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn build_rom(cartridge_type: u8, rom_size: u8, ram_size: u8) -> Vec<u8> {
+        let mut rom = vec![0; 0x8000 << rom_size];
+        rom[0x134..0x13a].copy_from_slice(b"TESTGB");
+        rom[0x147] = cartridge_type;
+        rom[0x148] = rom_size;
+        rom[0x149] = ram_size;
+        fix_checksums(&mut rom);
+        rom
+    }
+
+    pub(crate) fn fix_checksums(rom: &mut [u8]) {
+        fix_checksums_at(rom, 0);
+    }
+
+    pub(crate) fn fix_checksums_at(rom: &mut [u8], offset: usize) {
+        rom[offset + 0x14d] = header_checksum(&rom[offset..]);
+        let [hi, lo] = global_checksum(rom, offset).to_be_bytes();
+        rom[offset + 0x14e] = hi;
+        rom[offset + 0x14f] = lo;
+    }
+
+    #[test]
+    fn test_header() {
+        let mut rom = build_rom(0x09, 0x00, 0x02);
+        rom[0x143] = 0x80;
+        rom[0x146] = 0x03;
+        rom[0x14b] = 0x33;
+        rom[0x144] = b'0';
+        rom[0x145] = b'1';
+        rom[0x14c] = 0x02;
+        fix_checksums(&mut rom);
+        let cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        let header = cartridge.header();
+        assert_eq!(header.title, "TESTGB");
+        assert!(header.supports_cgb());
+        assert!(!header.cgb_only());
+        assert!(header.supports_sgb());
+        assert!(header.has_battery());
+        assert_eq!(header.cartridge_type, 0x09);
+        assert_eq!(header.rom_bytes(), Ok(0x8000));
+        assert_eq!(header.ram_bytes(), Ok(0x2000));
+        assert_eq!(header.licensee, Licensee::New([b'0', b'1']));
+        assert_eq!(header.version, 0x02);
+    }
+
+    #[test]
+    fn test_header_old_licensee() {
+        let mut rom = build_rom(0x00, 0x00, 0x00);
+        rom[0x14b] = 0x01;
+        fix_checksums(&mut rom);
+        let cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        assert_eq!(cartridge.header().licensee, Licensee::Old(0x01));
+        assert!(!cartridge.header().supports_cgb());
+        assert!(!cartridge.header().has_battery());
+    }
+
+    #[test]
+    fn test_too_small() {
+        let result = Cartridge::new(vec![0; 0x100].into_boxed_slice());
+        assert_eq!(result.err(), Some(Error::TooSmall(0x100)));
+    }
+
+    #[test]
+    fn test_header_checksum() {
+        let mut rom = build_rom(0x00, 0x00, 0x00);
+        rom[0x14d] ^= 0xff;
+        let result = Cartridge::new(rom.into_boxed_slice());
+        assert!(matches!(result.err(), Some(Error::HeaderChecksum { .. })));
+    }
+
+    #[test]
+    fn test_global_checksum() {
+        let mut rom = build_rom(0x00, 0x00, 0x00);
+        rom[0x4000] = 0x42;
+        let cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        assert_eq!(cartridge.read(0x4000), 0x42);
+        assert!(matches!(
+            cartridge.verify(),
+            Err(Error::GlobalChecksum { .. })
+        ));
+        let cartridge = Cartridge::new(build_rom(0x00, 0x00, 0x00).into_boxed_slice()).unwrap();
+        assert_eq!(cartridge.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut rom = build_rom(0x00, 0x00, 0x00);
+        rom[0x148] = 0x01;
+        fix_checksums(&mut rom);
+        let result = Cartridge::new(rom.into_boxed_slice());
+        assert_eq!(
+            result.err(),
+            Some(Error::Truncated {
+                expected: 0x10000,
+                actual: 0x8000
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_sizes() {
+        let mut rom = build_rom(0x00, 0x00, 0x00);
+        rom[0x148] = 0x52;
+        fix_checksums(&mut rom);
+        let result = Cartridge::new(rom.into_boxed_slice());
+        assert_eq!(result.err(), Some(Error::UnknownRomSize(0x52)));
+
+        let mut rom = build_rom(0x00, 0x00, 0x00);
+        rom[0x149] = 0x06;
+        fix_checksums(&mut rom);
+        let result = Cartridge::new(rom.into_boxed_slice());
+        assert_eq!(result.err(), Some(Error::UnknownRamSize(0x06)));
+    }
+
+    #[test]
+    fn test_unsupported_type() {
+        let rom = build_rom(0xfd, 0x00, 0x00);
+        let result = Cartridge::new(rom.into_boxed_slice());
+        assert_eq!(result.err(), Some(Error::UnsupportedType(0xfd)));
+    }
+
+    #[test]
+    fn test_unknown_type() {
+        let rom = build_rom(0x04, 0x00, 0x00);
+        let result = Cartridge::new(rom.into_boxed_slice());
+        assert_eq!(result.err(), Some(Error::UnknownType(0x04)));
+    }
+
+    #[test]
+    fn test_rom_only() {
+        let mut rom = build_rom(0x00, 0x00, 0x00);
+        rom[0x0100] = 0x12;
+        rom[0x7fff] = 0x34;
+        fix_checksums(&mut rom);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        assert_eq!(cartridge.read(0x0100), 0x12);
+        assert_eq!(cartridge.read(0x7fff), 0x34);
+        cartridge.write(0x7fff, 0x00);
+        assert_eq!(cartridge.read(0x7fff), 0x34);
+        cartridge.write(0xa000, 0x56);
+        assert_eq!(cartridge.read(0xa000), 0xff);
+    }
+
+    #[test]
+    fn test_rom_ram() {
+        let rom = build_rom(0x08, 0x00, 0x02);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0xa000, 0x56);
+        cartridge.write(0xbfff, 0x78);
+        assert_eq!(cartridge.read(0xa000), 0x56);
+        assert_eq!(cartridge.read(0xbfff), 0x78);
+    }
+}
@@ -0,0 +1,178 @@
+use super::{read_ram, read_rom, write_ram};
+
+pub const CAMERA_WIDTH: usize = 128;
+pub const CAMERA_HEIGHT: usize = 112;
+
+// Sensor exposure is not modelled; every capture takes the same time.
+const CAPTURE_MCYCLES: u32 = 32446;
+
+pub trait ImageSource {
+    // Fills a CAMERA_WIDTH x CAMERA_HEIGHT buffer of 8-bit luminance, row by row, 0 being black.
+    fn capture(&mut self, image: &mut [u8]);
+}
+
+struct Blank;
+impl ImageSource for Blank {
+    fn capture(&mut self, image: &mut [u8]) {
+        image.fill(0x80);
+    }
+}
+
+pub struct Camera {
+    ram_write: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+    registers: bool,
+    regs: [u8; 0x36],
+    busy: u32,
+    source: Box<dyn ImageSource>,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            ram_write: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            registers: false,
+            regs: [0; 0x36],
+            busy: 0,
+            source: Box::new(Blank),
+        }
+    }
+    pub fn set_source(&mut self, source: Box<dyn ImageSource>) {
+        self.source = source;
+    }
+    pub fn tick(&mut self, ram: &mut [u8]) {
+        if self.busy == 0 {
+            return;
+        }
+        self.busy -= 1;
+        if self.busy == 0 {
+            self.capture(ram);
+            self.regs[0] &= !0x01;
+        }
+    }
+    // Dithers the image through the 4x4 threshold matrix in registers 0x06-0x35 and stores it
+    // as 2bpp tiles from 0xa100 of RAM bank 0.
+    fn capture(&mut self, ram: &mut [u8]) {
+        let mut image = vec![0; CAMERA_WIDTH * CAMERA_HEIGHT];
+        self.source.capture(&mut image);
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let pixel = image[y * CAMERA_WIDTH + x];
+                let matrix = 0x06 + ((y & 3) * 4 + (x & 3)) * 3;
+                let thresholds = &self.regs[matrix..matrix + 3];
+                let color = thresholds.iter().filter(|&&t| pixel < t).count() as u8;
+                let tile = (y / 8) * (CAMERA_WIDTH / 8) + x / 8;
+                let i = 0x100 + tile * 16 + (y & 7) * 2;
+                let bit = 0x80 >> (x & 7);
+                if i + 1 >= ram.len() {
+                    continue;
+                }
+                ram[i] = if color & 0x01 > 0 {
+                    ram[i] | bit
+                } else {
+                    ram[i] & !bit
+                };
+                ram[i + 1] = if color & 0x02 > 0 {
+                    ram[i + 1] | bit
+                } else {
+                    ram[i + 1] & !bit
+                };
+            }
+        }
+    }
+    pub fn read(&self, rom: &[u8], ram: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3fff => read_rom(rom, 0, addr),
+            0x4000..=0x7fff => read_rom(rom, self.rom_bank as usize, addr),
+            0xa000..=0xbfff if self.registers => match addr & 0x7f {
+                0x00 => self.regs[0],
+                _ => 0x00,
+            },
+            0xa000..=0xbfff if self.busy > 0 => 0x00,
+            0xa000..=0xbfff => read_ram(ram, self.ram_bank as usize, addr),
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, ram: &mut [u8], addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_write = val & 0x0f == 0x0a,
+            0x2000..=0x3fff => self.rom_bank = val & 0x3f,
+            0x4000..=0x5fff => {
+                self.registers = val & 0x10 > 0;
+                self.ram_bank = val & 0x0f;
+            }
+            0xa000..=0xbfff if self.registers => match addr as usize & 0x7f {
+                0x00 => {
+                    if val & 0x01 > 0 && self.busy == 0 {
+                        self.busy = CAPTURE_MCYCLES;
+                    }
+                    self.regs[0] = (val & 0x06) | (self.regs[0] & 0x01) | (val & 0x01);
+                }
+                reg @ 0x01..=0x35 => self.regs[reg] = val,
+                _ => (),
+            },
+            0xa000..=0xbfff if self.ram_write && self.busy == 0 => {
+                write_ram(ram, self.ram_bank as usize, addr, val)
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{Cartridge, tests};
+
+    struct Gradient;
+    impl ImageSource for Gradient {
+        fn capture(&mut self, image: &mut [u8]) {
+            for (i, pixel) in image.iter_mut().enumerate() {
+                *pixel = (i % CAMERA_WIDTH * 2) as u8;
+            }
+        }
+    }
+
+    #[test]
+    fn test_camera_ram() {
+        let rom = tests::build_rom(0xfc, 0x00, 0x04);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0xa000, 0x12);
+        assert_eq!(cartridge.read(0xa000), 0x00);
+        cartridge.write(0x0000, 0x0a);
+        cartridge.write(0x4000, 0x0f);
+        cartridge.write(0xa000, 0x12);
+        assert_eq!(cartridge.read(0xa000), 0x12);
+        cartridge.write(0x4000, 0x10);
+        assert_eq!(cartridge.read(0xa000), 0x00);
+    }
+
+    #[test]
+    fn test_camera_capture() {
+        let rom = tests::build_rom(0xfc, 0x00, 0x04);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.set_camera_source(Box::new(Gradient));
+        cartridge.write(0x4000, 0x10);
+        for reg in 0..16 {
+            cartridge.write(0xa006 + reg * 3, 0x40);
+            cartridge.write(0xa007 + reg * 3, 0x80);
+            cartridge.write(0xa008 + reg * 3, 0xc0);
+        }
+        cartridge.write(0xa000, 0x01);
+        assert_eq!(cartridge.read(0xa000), 0x01);
+        for _ in 0..CAPTURE_MCYCLES {
+            cartridge.tick();
+        }
+        assert_eq!(cartridge.read(0xa000), 0x00);
+        cartridge.write(0x4000, 0x00);
+        assert_eq!(cartridge.read(0xa100), 0xff);
+        assert_eq!(cartridge.read(0xa101), 0xff);
+        assert_eq!(cartridge.read(0xa140), 0x00);
+        assert_eq!(cartridge.read(0xa141), 0xff);
+        assert_eq!(cartridge.read(0xa1f0), 0x00);
+        assert_eq!(cartridge.read(0xa1f1), 0x00);
+    }
+}
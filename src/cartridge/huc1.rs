@@ -0,0 +1,82 @@
+use super::{read_ram, read_rom, write_ram};
+
+pub struct HuC1 {
+    ir_mode: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+    ir_led: bool,
+    ir_light: bool,
+}
+
+impl HuC1 {
+    pub fn new() -> Self {
+        Self {
+            ir_mode: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            ir_led: false,
+            ir_light: false,
+        }
+    }
+    pub fn ir_led(&self) -> bool {
+        self.ir_led
+    }
+    pub fn set_ir_light(&mut self, light: bool) {
+        self.ir_light = light;
+    }
+    pub fn read(&self, rom: &[u8], ram: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3fff => read_rom(rom, 0, addr),
+            0x4000..=0x7fff => read_rom(rom, self.rom_bank as usize, addr),
+            0xa000..=0xbfff if self.ir_mode => 0xc0 | self.ir_light as u8,
+            0xa000..=0xbfff => read_ram(ram, self.ram_bank as usize, addr),
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, ram: &mut [u8], addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ir_mode = val & 0x0f == 0x0e,
+            0x2000..=0x3fff => self.rom_bank = if val & 0x3f == 0 { 1 } else { val & 0x3f },
+            0x4000..=0x5fff => self.ram_bank = val & 0x03,
+            0xa000..=0xbfff if self.ir_mode => self.ir_led = val & 0x01 > 0,
+            0xa000..=0xbfff => write_ram(ram, self.ram_bank as usize, addr, val),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cartridge::{Cartridge, tests};
+
+    #[test]
+    fn test_huc1_banks() {
+        let mut rom = tests::build_rom(0xff, 0x05, 0x03);
+        rom[0x3f * 0x4000] = 0x3f;
+        tests::fix_checksums(&mut rom);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x2000, 0x3f);
+        assert_eq!(cartridge.read(0x4000), 0x3f);
+        cartridge.write(0x4000, 0x02);
+        cartridge.write(0xa000, 0x12);
+        cartridge.write(0x4000, 0x00);
+        assert_eq!(cartridge.read(0xa000), 0x00);
+        cartridge.write(0x4000, 0x02);
+        assert_eq!(cartridge.read(0xa000), 0x12);
+    }
+
+    #[test]
+    fn test_huc1_ir() {
+        let rom = tests::build_rom(0xff, 0x00, 0x02);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x0000, 0x0e);
+        assert_eq!(cartridge.read(0xa000), 0xc0);
+        cartridge.set_ir_light(true);
+        assert_eq!(cartridge.read(0xa000), 0xc1);
+        cartridge.write(0xa000, 0x01);
+        assert!(cartridge.ir_led());
+        cartridge.write(0x0000, 0x0a);
+        assert_eq!(cartridge.read(0xa000), 0x00);
+        assert!(cartridge.ir_led());
+    }
+}
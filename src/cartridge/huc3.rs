@@ -0,0 +1,233 @@
+use super::{MCYCLES_PER_SECOND, read_ram, read_rom, write_ram};
+
+const MCYCLES_PER_MINUTE: u32 = MCYCLES_PER_SECOND * 60;
+const MINUTES_PER_DAY: u16 = 1440;
+
+pub struct HuC3 {
+    mode: u8,
+    rom_bank: u8,
+    ram_bank: u8,
+    index: u8,
+    result: u8,
+    ready: bool,
+    minutes: u16,
+    days: u16,
+    alarm_minutes: u16,
+    alarm_days: u16,
+    alarm_enable: bool,
+    cycles: u32,
+    ir_led: bool,
+    ir_light: bool,
+}
+
+impl HuC3 {
+    pub fn new() -> Self {
+        Self {
+            mode: 0,
+            rom_bank: 1,
+            ram_bank: 0,
+            index: 0,
+            result: 0,
+            ready: false,
+            minutes: 0,
+            days: 0,
+            alarm_minutes: 0,
+            alarm_days: 0,
+            alarm_enable: false,
+            cycles: 0,
+            ir_led: false,
+            ir_light: false,
+        }
+    }
+    pub fn ir_led(&self) -> bool {
+        self.ir_led
+    }
+    pub fn set_ir_light(&mut self, light: bool) {
+        self.ir_light = light;
+    }
+    pub fn tick(&mut self) {
+        self.cycles += 1;
+        if self.cycles == MCYCLES_PER_MINUTE {
+            self.cycles = 0;
+            self.advance_minutes(1);
+        }
+    }
+    fn advance_minutes(&mut self, minutes: u64) {
+        let total = self.minutes as u64 + minutes;
+        self.minutes = (total % MINUTES_PER_DAY as u64) as u16;
+        self.days = ((self.days as u64 + total / MINUTES_PER_DAY as u64) & 0xfff) as u16;
+    }
+    fn read_nibble(&self) -> u8 {
+        match self.index {
+            0x00..=0x02 => (self.minutes >> (self.index * 4)) as u8 & 0x0f,
+            0x03..=0x06 => (self.days >> ((self.index - 3) * 4)) as u8 & 0x0f,
+            _ => 0,
+        }
+    }
+    fn write_nibble(&mut self, val: u8) {
+        let val = val as u16 & 0x0f;
+        match self.index {
+            0x00..=0x02 => {
+                let shift = self.index * 4;
+                self.minutes = (self.minutes & !(0x0f << shift)) | (val << shift);
+            }
+            0x03..=0x06 => {
+                let shift = (self.index - 3) * 4;
+                self.days = (self.days & !(0x0f << shift)) | (val << shift);
+            }
+            0x58..=0x5a => {
+                let shift = (self.index - 0x58) * 4;
+                self.alarm_minutes = (self.alarm_minutes & !(0x0f << shift)) | (val << shift);
+            }
+            0x5b..=0x5e => {
+                let shift = (self.index - 0x5b) * 4;
+                self.alarm_days = (self.alarm_days & !(0x0f << shift)) | (val << shift);
+            }
+            0x5f => self.alarm_enable = val & 0x01 > 0,
+            _ => (),
+        }
+    }
+    // The high nibble of a command byte is the command and the low nibble its argument.
+    fn command(&mut self, val: u8) {
+        match val >> 4 {
+            0x1 => {
+                self.result = self.read_nibble();
+                self.index = self.index.wrapping_add(1);
+            }
+            0x2 => self.write_nibble(val),
+            0x3 => {
+                self.write_nibble(val);
+                self.index = self.index.wrapping_add(1);
+            }
+            0x4 => self.index = (self.index & 0xf0) | (val & 0x0f),
+            0x5 => self.index = (self.index & 0x0f) | ((val & 0x0f) << 4),
+            0x6 => self.ready = val & 0x0f == 0x02,
+            _ => (),
+        }
+    }
+    pub fn read(&self, rom: &[u8], ram: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3fff => read_rom(rom, 0, addr),
+            0x4000..=0x7fff => read_rom(rom, self.rom_bank as usize, addr),
+            0xa000..=0xbfff => match self.mode {
+                0x0 | 0xa => read_ram(ram, self.ram_bank as usize, addr),
+                0xc if self.ready => 0x01,
+                0xc => self.result,
+                0xe => 0xc0 | self.ir_light as u8,
+                _ => 0x01,
+            },
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, ram: &mut [u8], addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => self.mode = val & 0x0f,
+            0x2000..=0x3fff => self.rom_bank = val & 0x7f,
+            0x4000..=0x5fff => self.ram_bank = val & 0x03,
+            0xa000..=0xbfff => match self.mode {
+                0xa => write_ram(ram, self.ram_bank as usize, addr, val),
+                0xb => self.command(val),
+                0xe => self.ir_led = val & 0x01 > 0,
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    // Same layout as SameBoy's HuC3 footer: the UNIX time of the save as a u64, then the
+    // clock and alarm counters as u16s and the alarm enable byte, all little-endian.
+    pub fn save(&self, now: u64) -> Vec<u8> {
+        let mut data = Vec::with_capacity(17);
+        data.extend_from_slice(&now.to_le_bytes());
+        for val in [self.minutes, self.days, self.alarm_minutes, self.alarm_days] {
+            data.extend_from_slice(&val.to_le_bytes());
+        }
+        data.push(self.alarm_enable as u8);
+        data
+    }
+    pub fn load(&mut self, data: &[u8], now: u64) {
+        if data.len() < 17 {
+            return;
+        }
+        let timestamp = u64::from_le_bytes(data[0..8].try_into().unwrap());
+        let word = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        self.minutes = word(8) % MINUTES_PER_DAY;
+        self.days = word(10) & 0xfff;
+        self.alarm_minutes = word(12);
+        self.alarm_days = word(14);
+        self.alarm_enable = data[16] & 0x01 > 0;
+        self.cycles = 0;
+        self.advance_minutes(now.saturating_sub(timestamp) / 60);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{Cartridge, tests};
+
+    fn command(cartridge: &mut Cartridge, val: u8) -> u8 {
+        cartridge.write(0x0000, 0x0b);
+        cartridge.write(0xa000, val);
+        cartridge.write(0x0000, 0x0c);
+        cartridge.read(0xa000)
+    }
+
+    #[test]
+    fn test_huc3_ram() {
+        let rom = tests::build_rom(0xfe, 0x00, 0x03);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x0000, 0x0a);
+        cartridge.write(0x4000, 0x01);
+        cartridge.write(0xa000, 0x12);
+        cartridge.write(0x0000, 0x00);
+        cartridge.write(0xa000, 0x34);
+        assert_eq!(cartridge.read(0xa000), 0x12);
+        cartridge.write(0x0000, 0x0d);
+        assert_eq!(cartridge.read(0xa000), 0x01);
+    }
+
+    #[test]
+    fn test_huc3_rtc() {
+        let rom = tests::build_rom(0xfe, 0x00, 0x03);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        command(&mut cartridge, 0x40);
+        command(&mut cartridge, 0x50);
+        command(&mut cartridge, 0x3f);
+        command(&mut cartridge, 0x39);
+        command(&mut cartridge, 0x35);
+        command(&mut cartridge, 0x32);
+        for _ in 0..MCYCLES_PER_MINUTE {
+            cartridge.tick();
+        }
+        command(&mut cartridge, 0x40);
+        assert_eq!(command(&mut cartridge, 0x10), 0x0);
+        assert_eq!(command(&mut cartridge, 0x10), 0x0);
+        assert_eq!(command(&mut cartridge, 0x10), 0x0);
+        assert_eq!(command(&mut cartridge, 0x10), 0x3);
+        assert_eq!(command(&mut cartridge, 0x62), 0x01);
+    }
+
+    #[test]
+    fn test_huc3_ir() {
+        let rom = tests::build_rom(0xfe, 0x00, 0x03);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x0000, 0x0e);
+        cartridge.set_ir_light(true);
+        assert_eq!(cartridge.read(0xa000), 0xc1);
+        cartridge.write(0xa000, 0x01);
+        assert!(cartridge.ir_led());
+    }
+
+    #[test]
+    fn test_huc3_footer() {
+        let mut huc3 = HuC3::new();
+        huc3.minutes = MINUTES_PER_DAY - 1;
+        huc3.days = 7;
+        let data = huc3.save(1000);
+        assert_eq!(data.len(), 17);
+        let mut huc3 = HuC3::new();
+        huc3.load(&data, 1000 + 125);
+        assert_eq!(huc3.minutes, 1);
+        assert_eq!(huc3.days, 8);
+    }
+}
@@ -0,0 +1,163 @@
+use super::{NINTENDO_LOGO, read_ram, read_rom, write_ram};
+
+pub struct Mbc1 {
+    ram_enable: bool,
+    bank1: u8,
+    bank2: u8,
+    mode: bool,
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: &[u8]) -> Self {
+        Self {
+            ram_enable: false,
+            bank1: 1,
+            bank2: 0,
+            mode: false,
+            multicart: is_multicart(rom),
+        }
+    }
+    fn upper_shift(&self) -> u8 {
+        if self.multicart { 4 } else { 5 }
+    }
+    fn rom_bank0(&self) -> usize {
+        if self.mode {
+            (self.bank2 << self.upper_shift()) as usize
+        } else {
+            0
+        }
+    }
+    fn rom_bank1(&self) -> usize {
+        let bank1 = if self.multicart {
+            self.bank1 & 0x0f
+        } else {
+            self.bank1
+        };
+        ((self.bank2 << self.upper_shift()) | bank1) as usize
+    }
+    fn ram_bank(&self) -> usize {
+        if self.mode { self.bank2 as usize } else { 0 }
+    }
+    pub fn read(&self, rom: &[u8], ram: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3fff => read_rom(rom, self.rom_bank0(), addr),
+            0x4000..=0x7fff => read_rom(rom, self.rom_bank1(), addr),
+            0xa000..=0xbfff if self.ram_enable => read_ram(ram, self.ram_bank(), addr),
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, ram: &mut [u8], addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_enable = val & 0x0f == 0x0a,
+            0x2000..=0x3fff => self.bank1 = if val & 0x1f == 0 { 1 } else { val & 0x1f },
+            0x4000..=0x5fff => self.bank2 = val & 0x03,
+            0x6000..=0x7fff => self.mode = val & 0x01 > 0,
+            0xa000..=0xbfff if self.ram_enable => write_ram(ram, self.ram_bank(), addr, val),
+            _ => (),
+        }
+    }
+}
+
+// Multicarts are 1 MiB MBC1 boards with bit 4 of the bank number left unconnected, so each
+// 256 KiB game starts with its own header; a second Nintendo logo at bank 0x10 gives them away.
+fn is_multicart(rom: &[u8]) -> bool {
+    rom.len() == 0x100000 && rom[0x40104..0x40134] == NINTENDO_LOGO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{Cartridge, tests};
+
+    fn banked_rom(rom_size: u8, ram_size: u8) -> Vec<u8> {
+        let mut rom = tests::build_rom(0x03, rom_size, ram_size);
+        for bank in 1..rom.len() / 0x4000 {
+            rom[bank * 0x4000] = bank as u8;
+        }
+        tests::fix_checksums(&mut rom);
+        rom
+    }
+
+    #[test]
+    fn test_mbc1_rom_bank() {
+        let rom = banked_rom(0x04, 0x00);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        assert_eq!(cartridge.read(0x4000), 1);
+        cartridge.write(0x2000, 0x05);
+        assert_eq!(cartridge.read(0x4000), 5);
+        cartridge.write(0x2000, 0x00);
+        assert_eq!(cartridge.read(0x4000), 1);
+        cartridge.write(0x2000, 0x3f);
+        assert_eq!(cartridge.read(0x4000), 0x1f);
+    }
+
+    #[test]
+    fn test_mbc1_upper_bank() {
+        let rom = banked_rom(0x06, 0x00);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x4000, 0x01);
+        cartridge.write(0x2000, 0x00);
+        assert_eq!(cartridge.read(0x4000), 0x21);
+        cartridge.write(0x4000, 0x03);
+        cartridge.write(0x2000, 0x02);
+        assert_eq!(cartridge.read(0x4000), 0x62);
+        assert_eq!(cartridge.read(0x0000), 0x00);
+        cartridge.write(0x6000, 0x01);
+        assert_eq!(cartridge.read(0x0000), 0x60);
+    }
+
+    #[test]
+    fn test_mbc1_rom_bank_wraps() {
+        let rom = banked_rom(0x01, 0x00);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x2000, 0x05);
+        assert_eq!(cartridge.read(0x4000), 1);
+    }
+
+    #[test]
+    fn test_mbc1_ram() {
+        let rom = banked_rom(0x00, 0x03);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0xa000, 0x12);
+        assert_eq!(cartridge.read(0xa000), 0xff);
+        cartridge.write(0x0000, 0x0a);
+        cartridge.write(0xa000, 0x12);
+        assert_eq!(cartridge.read(0xa000), 0x12);
+        cartridge.write(0x4000, 0x02);
+        assert_eq!(cartridge.read(0xa000), 0x12);
+        cartridge.write(0x6000, 0x01);
+        assert_eq!(cartridge.read(0xa000), 0x00);
+        cartridge.write(0xa000, 0x34);
+        cartridge.write(0x6000, 0x00);
+        assert_eq!(cartridge.read(0xa000), 0x12);
+        cartridge.write(0x0000, 0x00);
+        assert_eq!(cartridge.read(0xa000), 0xff);
+    }
+
+    #[test]
+    fn test_mbc1_multicart() {
+        let mut rom = banked_rom(0x05, 0x00);
+        rom[0x104..0x134].copy_from_slice(&NINTENDO_LOGO);
+        rom[0x40104..0x40134].copy_from_slice(&NINTENDO_LOGO);
+        tests::fix_checksums(&mut rom);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x2000, 0x12);
+        assert_eq!(cartridge.read(0x4000), 0x02);
+        cartridge.write(0x2000, 0x10);
+        assert_eq!(cartridge.read(0x4000), 0x00);
+        cartridge.write(0x4000, 0x01);
+        cartridge.write(0x2000, 0x03);
+        assert_eq!(cartridge.read(0x4000), 0x13);
+        cartridge.write(0x6000, 0x01);
+        assert_eq!(cartridge.read(0x0000), 0x10);
+    }
+
+    #[test]
+    fn test_mbc1_not_multicart() {
+        let rom = banked_rom(0x05, 0x00);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x2000, 0x12);
+        assert_eq!(cartridge.read(0x4000), 0x12);
+    }
+}
@@ -0,0 +1,68 @@
+use super::read_rom;
+
+pub struct Mbc2 {
+    ram_enable: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub fn new() -> Self {
+        Self {
+            ram_enable: false,
+            rom_bank: 1,
+        }
+    }
+    pub fn read(&self, rom: &[u8], ram: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3fff => read_rom(rom, 0, addr),
+            0x4000..=0x7fff => read_rom(rom, self.rom_bank as usize, addr),
+            0xa000..=0xbfff if self.ram_enable => ram[(addr & 0x1ff) as usize] | 0xf0,
+            _ => 0xff,
+        }
+    }
+    // Bit 8 of the address picks the register for the whole 0x0000-0x3fff range.
+    pub fn write(&mut self, ram: &mut [u8], addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x3fff if addr & 0x100 == 0 => self.ram_enable = val & 0x0f == 0x0a,
+            0x0000..=0x3fff => self.rom_bank = if val & 0x0f == 0 { 1 } else { val & 0x0f },
+            0xa000..=0xbfff if self.ram_enable => ram[(addr & 0x1ff) as usize] = val & 0x0f,
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cartridge::{Cartridge, tests};
+
+    #[test]
+    fn test_mbc2_rom_bank() {
+        let mut rom = tests::build_rom(0x05, 0x03, 0x00);
+        rom[0x4000] = 0x01;
+        rom[0x0f * 0x4000] = 0x0f;
+        tests::fix_checksums(&mut rom);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x2100, 0x0f);
+        assert_eq!(cartridge.read(0x4000), 0x0f);
+        cartridge.write(0x2000, 0x00);
+        assert_eq!(cartridge.read(0x4000), 0x0f);
+        cartridge.write(0x3f00, 0x10);
+        assert_eq!(cartridge.read(0x4000), 0x01);
+    }
+
+    #[test]
+    fn test_mbc2_ram() {
+        let rom = tests::build_rom(0x06, 0x00, 0x00);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0xa000, 0x05);
+        assert_eq!(cartridge.read(0xa000), 0xff);
+        cartridge.write(0x0100, 0x0a);
+        cartridge.write(0xa000, 0x05);
+        assert_eq!(cartridge.read(0xa000), 0xff);
+        cartridge.write(0x0000, 0x0a);
+        cartridge.write(0xa000, 0x35);
+        assert_eq!(cartridge.read(0xa000), 0xf5);
+        assert_eq!(cartridge.read(0xa200), 0xf5);
+        assert_eq!(cartridge.save_data().len(), 0x200);
+    }
+}
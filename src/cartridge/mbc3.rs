@@ -0,0 +1,364 @@
+use super::{MCYCLES_PER_SECOND, read_ram, read_rom, write_ram};
+
+const SECONDS: usize = 0;
+const MINUTES: usize = 1;
+const HOURS: usize = 2;
+const DAY_LOW: usize = 3;
+const DAY_HIGH: usize = 4;
+
+const MASKS: [u8; 5] = [0x3f, 0x3f, 0x1f, 0xff, 0xc1];
+
+#[derive(Default)]
+pub struct Rtc {
+    regs: [u8; 5],
+    latched: [u8; 5],
+    cycles: u32,
+}
+
+impl Rtc {
+    fn halted(&self) -> bool {
+        self.regs[DAY_HIGH] & 0x40 > 0
+    }
+    fn day(&self) -> u16 {
+        ((self.regs[DAY_HIGH] as u16 & 0x01) << 8) | self.regs[DAY_LOW] as u16
+    }
+    fn set_day(&mut self, day: u16) {
+        self.regs[DAY_LOW] = day as u8;
+        self.regs[DAY_HIGH] = (self.regs[DAY_HIGH] & 0xfe) | (day >> 8) as u8 & 0x01;
+    }
+    pub fn tick(&mut self) {
+        if self.halted() {
+            return;
+        }
+        self.cycles += 1;
+        if self.cycles == MCYCLES_PER_SECOND {
+            self.cycles = 0;
+            self.tick_second();
+        }
+    }
+    // Each counter wraps at its register width, so only a value that passes through the
+    // normal limit carries into the next one.
+    fn tick_second(&mut self) {
+        self.regs[SECONDS] = (self.regs[SECONDS] + 1) & 0x3f;
+        if self.regs[SECONDS] != 60 {
+            return;
+        }
+        self.regs[SECONDS] = 0;
+        self.regs[MINUTES] = (self.regs[MINUTES] + 1) & 0x3f;
+        if self.regs[MINUTES] != 60 {
+            return;
+        }
+        self.regs[MINUTES] = 0;
+        self.regs[HOURS] = (self.regs[HOURS] + 1) & 0x1f;
+        if self.regs[HOURS] != 24 {
+            return;
+        }
+        self.regs[HOURS] = 0;
+        let day = self.day() + 1;
+        if day > 0x1ff {
+            self.regs[DAY_HIGH] |= 0x80;
+        }
+        self.set_day(day & 0x1ff);
+    }
+    fn advance(&mut self, mut secs: u64) {
+        if self.halted() {
+            return;
+        }
+        while secs > 0
+            && (self.regs[SECONDS] >= 60 || self.regs[MINUTES] >= 60 || self.regs[HOURS] >= 24)
+        {
+            self.tick_second();
+            secs -= 1;
+        }
+        if secs == 0 {
+            return;
+        }
+        let total = secs
+            + self.regs[SECONDS] as u64
+            + self.regs[MINUTES] as u64 * 60
+            + self.regs[HOURS] as u64 * 3600
+            + self.day() as u64 * 86400;
+        self.regs[SECONDS] = (total % 60) as u8;
+        self.regs[MINUTES] = (total / 60 % 60) as u8;
+        self.regs[HOURS] = (total / 3600 % 24) as u8;
+        let days = total / 86400;
+        if days > 0x1ff {
+            self.regs[DAY_HIGH] |= 0x80;
+        }
+        self.set_day((days & 0x1ff) as u16);
+    }
+    fn latch(&mut self) {
+        self.latched = self.regs;
+    }
+    fn read(&self, reg: usize) -> u8 {
+        self.latched[reg] & MASKS[reg]
+    }
+    fn write(&mut self, reg: usize, val: u8) {
+        if reg == SECONDS {
+            self.cycles = 0;
+        }
+        self.regs[reg] = val & MASKS[reg];
+    }
+    // The footer shared by most emulators: the live and latched registers as little-endian
+    // u32s, followed by the UNIX time of the save as a little-endian u64.
+    pub fn save(&self, now: u64) -> Vec<u8> {
+        let mut data = Vec::with_capacity(48);
+        for reg in self.regs.iter().chain(self.latched.iter()) {
+            data.extend_from_slice(&(*reg as u32).to_le_bytes());
+        }
+        data.extend_from_slice(&now.to_le_bytes());
+        data
+    }
+    pub fn load(&mut self, data: &[u8], now: u64) {
+        if data.len() < 44 {
+            return;
+        }
+        for (i, chunk) in data[..40].chunks(4).enumerate() {
+            let val = chunk[0] & MASKS[i % 5];
+            if i < 5 {
+                self.regs[i] = val;
+            } else {
+                self.latched[i - 5] = val;
+            }
+        }
+        let timestamp = if data.len() >= 48 {
+            u64::from_le_bytes(data[40..48].try_into().unwrap())
+        } else {
+            u32::from_le_bytes(data[40..44].try_into().unwrap()) as u64
+        };
+        self.cycles = 0;
+        self.advance(now.saturating_sub(timestamp));
+    }
+}
+
+pub struct Mbc3 {
+    ram_enable: bool,
+    rom_bank: u8,
+    select: u8,
+    latch: u8,
+    mbc30: bool,
+    rtc: Option<Rtc>,
+}
+
+impl Mbc3 {
+    pub fn new(rom: &[u8], ram: &[u8], rtc: bool) -> Self {
+        Self {
+            ram_enable: false,
+            rom_bank: 1,
+            select: 0,
+            latch: 0xff,
+            mbc30: rom.len() > 0x200000 || ram.len() > 0x8000,
+            rtc: if rtc { Some(Rtc::default()) } else { None },
+        }
+    }
+    pub fn rtc(&self) -> Option<&Rtc> {
+        self.rtc.as_ref()
+    }
+    pub fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+        self.rtc.as_mut()
+    }
+    pub fn tick(&mut self) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.tick();
+        }
+    }
+    pub fn read(&self, rom: &[u8], ram: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3fff => read_rom(rom, 0, addr),
+            0x4000..=0x7fff => read_rom(rom, self.rom_bank as usize, addr),
+            0xa000..=0xbfff if self.ram_enable => match (self.select, &self.rtc) {
+                (0x00..=0x07, _) => read_ram(ram, self.select as usize, addr),
+                (0x08..=0x0c, Some(rtc)) => rtc.read((self.select - 0x08) as usize),
+                _ => 0xff,
+            },
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, ram: &mut [u8], addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_enable = val & 0x0f == 0x0a,
+            0x2000..=0x3fff => {
+                let bank = if self.mbc30 { val } else { val & 0x7f };
+                self.rom_bank = if bank == 0 { 1 } else { bank };
+            }
+            0x4000..=0x5fff => self.select = val & 0x0f,
+            0x6000..=0x7fff => {
+                if self.latch == 0x00
+                    && val == 0x01
+                    && let Some(rtc) = &mut self.rtc
+                {
+                    rtc.latch();
+                }
+                self.latch = val;
+            }
+            0xa000..=0xbfff if self.ram_enable => match (self.select, &mut self.rtc) {
+                (0x00..=0x07, _) => write_ram(ram, self.select as usize, addr, val),
+                (0x08..=0x0c, Some(rtc)) => rtc.write((self.select - 0x08) as usize, val),
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{Cartridge, tests};
+
+    fn rtc_cartridge() -> Cartridge {
+        let rom = tests::build_rom(0x10, 0x02, 0x03);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x0000, 0x0a);
+        cartridge
+    }
+
+    fn latch(cartridge: &mut Cartridge) {
+        cartridge.write(0x6000, 0x00);
+        cartridge.write(0x6000, 0x01);
+    }
+
+    fn read_rtc(cartridge: &mut Cartridge, reg: u8) -> u8 {
+        cartridge.write(0x4000, reg);
+        cartridge.read(0xa000)
+    }
+
+    fn write_rtc(cartridge: &mut Cartridge, reg: u8, val: u8) {
+        cartridge.write(0x4000, reg);
+        cartridge.write(0xa000, val);
+    }
+
+    #[test]
+    fn test_mbc3_rom_bank() {
+        let mut rom = tests::build_rom(0x11, 0x06, 0x00);
+        rom[0x7f * 0x4000] = 0x7f;
+        rom[0x4000] = 0x01;
+        tests::fix_checksums(&mut rom);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x2000, 0x7f);
+        assert_eq!(cartridge.read(0x4000), 0x7f);
+        cartridge.write(0x2000, 0x00);
+        assert_eq!(cartridge.read(0x4000), 0x01);
+        cartridge.write(0x2000, 0x80);
+        assert_eq!(cartridge.read(0x4000), 0x01);
+    }
+
+    #[test]
+    fn test_mbc3_ram_bank() {
+        let mut cartridge = rtc_cartridge();
+        cartridge.write(0x4000, 0x00);
+        cartridge.write(0xa000, 0x12);
+        cartridge.write(0x4000, 0x03);
+        cartridge.write(0xa000, 0x34);
+        assert_eq!(cartridge.read(0xa000), 0x34);
+        cartridge.write(0x4000, 0x00);
+        assert_eq!(cartridge.read(0xa000), 0x12);
+        cartridge.write(0x0000, 0x00);
+        assert_eq!(cartridge.read(0xa000), 0xff);
+    }
+
+    #[test]
+    fn test_mbc3_rtc_latch() {
+        let mut cartridge = rtc_cartridge();
+        for _ in 0..MCYCLES_PER_SECOND * 2 {
+            cartridge.tick();
+        }
+        assert_eq!(read_rtc(&mut cartridge, 0x08), 0);
+        latch(&mut cartridge);
+        assert_eq!(read_rtc(&mut cartridge, 0x08), 2);
+        for _ in 0..MCYCLES_PER_SECOND {
+            cartridge.tick();
+        }
+        assert_eq!(read_rtc(&mut cartridge, 0x08), 2);
+        cartridge.write(0x6000, 0x01);
+        assert_eq!(read_rtc(&mut cartridge, 0x08), 2);
+        latch(&mut cartridge);
+        assert_eq!(read_rtc(&mut cartridge, 0x08), 3);
+    }
+
+    #[test]
+    fn test_mbc3_rtc_carry() {
+        let mut cartridge = rtc_cartridge();
+        write_rtc(&mut cartridge, 0x08, 59);
+        write_rtc(&mut cartridge, 0x09, 59);
+        write_rtc(&mut cartridge, 0x0a, 23);
+        write_rtc(&mut cartridge, 0x0b, 0xff);
+        write_rtc(&mut cartridge, 0x0c, 0x01);
+        for _ in 0..MCYCLES_PER_SECOND {
+            cartridge.tick();
+        }
+        latch(&mut cartridge);
+        assert_eq!(read_rtc(&mut cartridge, 0x08), 0);
+        assert_eq!(read_rtc(&mut cartridge, 0x09), 0);
+        assert_eq!(read_rtc(&mut cartridge, 0x0a), 0);
+        assert_eq!(read_rtc(&mut cartridge, 0x0b), 0);
+        assert_eq!(read_rtc(&mut cartridge, 0x0c), 0x80);
+    }
+
+    #[test]
+    fn test_mbc3_rtc_out_of_range() {
+        let mut rtc = Rtc::default();
+        rtc.write(SECONDS, 62);
+        rtc.tick_second();
+        assert_eq!(rtc.regs[SECONDS], 63);
+        rtc.tick_second();
+        assert_eq!(rtc.regs[SECONDS], 0);
+        assert_eq!(rtc.regs[MINUTES], 0);
+    }
+
+    #[test]
+    fn test_mbc3_rtc_halt() {
+        let mut cartridge = rtc_cartridge();
+        write_rtc(&mut cartridge, 0x0c, 0x40);
+        for _ in 0..MCYCLES_PER_SECOND {
+            cartridge.tick();
+        }
+        latch(&mut cartridge);
+        assert_eq!(read_rtc(&mut cartridge, 0x08), 0);
+        assert_eq!(read_rtc(&mut cartridge, 0x0c), 0x40);
+    }
+
+    #[test]
+    fn test_mbc3_rtc_footer() {
+        let mut rtc = Rtc::default();
+        rtc.write(SECONDS, 30);
+        rtc.write(MINUTES, 59);
+        rtc.write(HOURS, 23);
+        rtc.write(DAY_LOW, 0x10);
+        rtc.latch();
+        let data = rtc.save(1000);
+        assert_eq!(data.len(), 48);
+        assert_eq!(data[0..4], [30, 0, 0, 0]);
+        assert_eq!(data[20..24], [30, 0, 0, 0]);
+        assert_eq!(data[40..48], 1000u64.to_le_bytes());
+
+        let mut rtc = Rtc::default();
+        rtc.load(&data, 1000 + 31);
+        assert_eq!(rtc.regs, [1, 0, 0, 0x11, 0]);
+        assert_eq!(rtc.latched, [30, 59, 23, 0x10, 0]);
+
+        let mut rtc = Rtc::default();
+        rtc.load(&data[..44], 1000 + 86400 * 0x1f0);
+        assert_eq!(rtc.regs, [30, 59, 23, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn test_mbc3_save_data() {
+        let mut cartridge = rtc_cartridge();
+        cartridge.write(0xa000, 0x42);
+        write_rtc(&mut cartridge, 0x09, 12);
+        let data = cartridge.save_data();
+        assert_eq!(data.len(), 0x8000 + 48);
+        assert_eq!(data[0], 0x42);
+        assert_eq!(data[0x8004], 12);
+
+        let mut cartridge =
+            Cartridge::new(tests::build_rom(0x10, 0x02, 0x03).into_boxed_slice()).unwrap();
+        cartridge.load_save_data(&data);
+        cartridge.write(0x0000, 0x0a);
+        cartridge.write(0x4000, 0x00);
+        assert_eq!(cartridge.read(0xa000), 0x42);
+        latch(&mut cartridge);
+        assert_eq!(read_rtc(&mut cartridge, 0x09), 12);
+    }
+}
@@ -0,0 +1,100 @@
+use super::{read_ram, read_rom, write_ram};
+
+pub struct Mbc5 {
+    ram_enable: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+    has_rumble: bool,
+    rumble: bool,
+}
+
+impl Mbc5 {
+    pub fn new(has_rumble: bool) -> Self {
+        Self {
+            ram_enable: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            rumble: false,
+        }
+    }
+    pub fn rumble(&self) -> bool {
+        self.rumble
+    }
+    pub fn read(&self, rom: &[u8], ram: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3fff => read_rom(rom, 0, addr),
+            0x4000..=0x7fff => read_rom(rom, self.rom_bank as usize, addr),
+            0xa000..=0xbfff if self.ram_enable => read_ram(ram, self.ram_bank as usize, addr),
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, ram: &mut [u8], addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_enable = val == 0x0a,
+            0x2000..=0x2fff => self.rom_bank = (self.rom_bank & 0x100) | val as u16,
+            0x3000..=0x3fff => self.rom_bank = (self.rom_bank & 0xff) | ((val as u16 & 0x01) << 8),
+            // Carts with a motor drive it from bit 3, leaving only eight RAM banks.
+            0x4000..=0x5fff if self.has_rumble => {
+                self.rumble = val & 0x08 > 0;
+                self.ram_bank = val & 0x07;
+            }
+            0x4000..=0x5fff => self.ram_bank = val & 0x0f,
+            0xa000..=0xbfff if self.ram_enable => write_ram(ram, self.ram_bank as usize, addr, val),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cartridge::{Cartridge, tests};
+
+    #[test]
+    fn test_mbc5_rom_bank() {
+        let mut rom = tests::build_rom(0x19, 0x08, 0x00);
+        rom[0x4000] = 0x01;
+        rom[0xff * 0x4000] = 0xff;
+        rom[0x100 * 0x4000] = 0x80;
+        rom[0x1ff * 0x4000] = 0x7f;
+        tests::fix_checksums(&mut rom);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        assert_eq!(cartridge.read(0x4000), 0x01);
+        cartridge.write(0x2000, 0xff);
+        assert_eq!(cartridge.read(0x4000), 0xff);
+        cartridge.write(0x3000, 0x01);
+        assert_eq!(cartridge.read(0x4000), 0x7f);
+        cartridge.write(0x2000, 0x00);
+        assert_eq!(cartridge.read(0x4000), 0x80);
+        cartridge.write(0x3000, 0x00);
+        assert_eq!(cartridge.read(0x4000), 0x00);
+    }
+
+    #[test]
+    fn test_mbc5_ram_bank() {
+        let rom = tests::build_rom(0x1b, 0x00, 0x04);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x0000, 0x0a);
+        cartridge.write(0x4000, 0x0f);
+        cartridge.write(0xa000, 0x12);
+        cartridge.write(0x4000, 0x00);
+        assert_eq!(cartridge.read(0xa000), 0x00);
+        cartridge.write(0x4000, 0x0f);
+        assert_eq!(cartridge.read(0xa000), 0x12);
+        assert!(!cartridge.rumble());
+    }
+
+    #[test]
+    fn test_mbc5_rumble() {
+        let rom = tests::build_rom(0x1e, 0x00, 0x03);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x0000, 0x0a);
+        cartridge.write(0x4000, 0x03);
+        cartridge.write(0xa000, 0x34);
+        cartridge.write(0x4000, 0x0b);
+        assert!(cartridge.rumble());
+        assert_eq!(cartridge.read(0xa000), 0x34);
+        cartridge.write(0x4000, 0x03);
+        assert!(!cartridge.rumble());
+    }
+}
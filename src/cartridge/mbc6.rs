@@ -0,0 +1,221 @@
+use super::read_rom;
+
+const FLASH_BYTES: usize = 0x100000;
+const SECTOR_BYTES: usize = 0x20000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FlashState {
+    Ready,
+    Unlock1,
+    Unlock2,
+    Program,
+    Erase,
+    EraseUnlock1,
+    EraseUnlock2,
+}
+
+#[derive(Clone, Copy)]
+struct Bank {
+    number: u8,
+    flash: bool,
+}
+
+// Two independent 8 KiB windows at 0x4000 and 0x6000, each mapping ROM or the 1 MiB flash,
+// and two 4 KiB RAM windows at 0xa000 and 0xb000.
+pub struct Mbc6 {
+    ram_enable: bool,
+    ram_banks: [u8; 2],
+    flash_enable: bool,
+    flash_write: bool,
+    banks: [Bank; 2],
+    state: FlashState,
+    flash: Box<[u8]>,
+}
+
+impl Mbc6 {
+    pub fn new() -> Self {
+        Self {
+            ram_enable: false,
+            ram_banks: [0; 2],
+            flash_enable: false,
+            flash_write: false,
+            banks: [Bank {
+                number: 0,
+                flash: false,
+            }; 2],
+            state: FlashState::Ready,
+            flash: vec![0xff; FLASH_BYTES].into_boxed_slice(),
+        }
+    }
+    pub fn flash(&self) -> &[u8] {
+        &self.flash
+    }
+    pub fn load_flash(&mut self, data: &[u8]) {
+        let len = data.len().min(FLASH_BYTES);
+        self.flash[..len].copy_from_slice(&data[..len]);
+    }
+    fn flash_addr(bank: Bank, addr: u16) -> usize {
+        (bank.number as usize * 0x2000 + (addr & 0x1fff) as usize) % FLASH_BYTES
+    }
+    fn ram_addr(&self, ram: &[u8], addr: u16) -> usize {
+        let bank = self.ram_banks[(addr >> 12) as usize & 1];
+        (bank as usize * 0x1000 + (addr & 0x0fff) as usize) % ram.len()
+    }
+    pub fn read(&self, rom: &[u8], ram: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3fff => read_rom(rom, 0, addr),
+            0x4000..=0x7fff => {
+                let bank = self.banks[(addr >> 13) as usize & 1];
+                if bank.flash {
+                    if !self.flash_enable {
+                        return 0xff;
+                    }
+                    self.flash[Self::flash_addr(bank, addr)]
+                } else {
+                    let offset = bank.number as usize * 0x2000 + (addr & 0x1fff) as usize;
+                    rom[offset % rom.len()]
+                }
+            }
+            0xa000..=0xbfff if self.ram_enable && !ram.is_empty() => ram[self.ram_addr(ram, addr)],
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, ram: &mut [u8], addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x03ff => self.ram_enable = val & 0x0f == 0x0a,
+            0x0400..=0x07ff => self.ram_banks[0] = val,
+            0x0800..=0x0bff => self.ram_banks[1] = val,
+            0x0c00..=0x0fff => self.flash_enable = val & 0x01 > 0,
+            0x1000 => self.flash_write = val & 0x01 > 0,
+            0x2000..=0x27ff => self.banks[0].number = val,
+            0x2800..=0x2fff => self.banks[0].flash = val == 0x08,
+            0x3000..=0x37ff => self.banks[1].number = val,
+            0x3800..=0x3fff => self.banks[1].flash = val == 0x08,
+            0x4000..=0x7fff => {
+                let bank = self.banks[(addr >> 13) as usize & 1];
+                if bank.flash && self.flash_enable {
+                    self.flash_command(Self::flash_addr(bank, addr), val);
+                }
+            }
+            0xa000..=0xbfff if self.ram_enable && !ram.is_empty() => {
+                let i = self.ram_addr(ram, addr);
+                ram[i] = val;
+            }
+            _ => (),
+        }
+    }
+    // The usual JEDEC sequences: 0xaa to 0x5555 and 0x55 to 0x2aaa unlock a command.
+    fn flash_command(&mut self, addr: usize, val: u8) {
+        let unlock = addr & 0x7fff;
+        self.state = match (self.state, unlock, val) {
+            (_, _, 0xf0) => FlashState::Ready,
+            (FlashState::Ready, 0x5555, 0xaa) => FlashState::Unlock1,
+            (FlashState::Unlock1, 0x2aaa, 0x55) => FlashState::Unlock2,
+            (FlashState::Unlock2, 0x5555, 0xa0) => FlashState::Program,
+            (FlashState::Unlock2, 0x5555, 0x80) => FlashState::Erase,
+            (FlashState::Program, _, _) => {
+                if self.flash_write {
+                    self.flash[addr] &= val;
+                }
+                FlashState::Ready
+            }
+            (FlashState::Erase, 0x5555, 0xaa) => FlashState::EraseUnlock1,
+            (FlashState::EraseUnlock1, 0x2aaa, 0x55) => FlashState::EraseUnlock2,
+            (FlashState::EraseUnlock2, _, 0x30) => {
+                if self.flash_write {
+                    let start = addr & !(SECTOR_BYTES - 1);
+                    self.flash[start..start + SECTOR_BYTES].fill(0xff);
+                }
+                FlashState::Ready
+            }
+            (FlashState::EraseUnlock2, 0x5555, 0x10) => {
+                if self.flash_write {
+                    self.flash.fill(0xff);
+                }
+                FlashState::Ready
+            }
+            _ => FlashState::Ready,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cartridge::{Cartridge, tests};
+
+    fn flash_cartridge() -> Cartridge {
+        let rom = tests::build_rom(0x20, 0x02, 0x03);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x0c00, 0x01);
+        cartridge.write(0x1000, 0x01);
+        cartridge.write(0x2800, 0x08);
+        cartridge.write(0x3800, 0x08);
+        cartridge
+    }
+
+    fn unlock(cartridge: &mut Cartridge) {
+        cartridge.write(0x2000, 0x02);
+        cartridge.write(0x5555, 0xaa);
+        cartridge.write(0x3000, 0x01);
+        cartridge.write(0x6aaa, 0x55);
+    }
+
+    #[test]
+    fn test_mbc6_rom_banks() {
+        let mut rom = tests::build_rom(0x20, 0x02, 0x00);
+        rom[0x2000 * 5] = 0x05;
+        rom[0x2000 * 9] = 0x09;
+        tests::fix_checksums(&mut rom);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x2000, 0x05);
+        cartridge.write(0x3000, 0x09);
+        assert_eq!(cartridge.read(0x4000), 0x05);
+        assert_eq!(cartridge.read(0x6000), 0x09);
+    }
+
+    #[test]
+    fn test_mbc6_ram_banks() {
+        let rom = tests::build_rom(0x20, 0x00, 0x03);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x0000, 0x0a);
+        cartridge.write(0x0400, 0x03);
+        cartridge.write(0x0800, 0x05);
+        cartridge.write(0xa000, 0x12);
+        cartridge.write(0xb000, 0x34);
+        cartridge.write(0x0400, 0x05);
+        assert_eq!(cartridge.read(0xa000), 0x34);
+        cartridge.write(0x0800, 0x03);
+        assert_eq!(cartridge.read(0xb000), 0x12);
+    }
+
+    #[test]
+    fn test_mbc6_flash_program() {
+        let mut cartridge = flash_cartridge();
+        unlock(&mut cartridge);
+        cartridge.write(0x2000, 0x02);
+        cartridge.write(0x5555, 0xa0);
+        cartridge.write(0x2000, 0x10);
+        cartridge.write(0x4010, 0x5a);
+        assert_eq!(cartridge.read(0x4010), 0x5a);
+        assert_eq!(cartridge.save_data()[0x8000 + 0x20010], 0x5a);
+        cartridge.write(0x4010, 0xff);
+        assert_eq!(cartridge.read(0x4010), 0x5a);
+    }
+
+    #[test]
+    fn test_mbc6_flash_erase() {
+        let mut cartridge = flash_cartridge();
+        unlock(&mut cartridge);
+        cartridge.write(0x2000, 0x02);
+        cartridge.write(0x5555, 0xa0);
+        cartridge.write(0x2000, 0x10);
+        cartridge.write(0x4010, 0x00);
+        unlock(&mut cartridge);
+        cartridge.write(0x2000, 0x02);
+        cartridge.write(0x5555, 0x80);
+        unlock(&mut cartridge);
+        cartridge.write(0x2000, 0x10);
+        cartridge.write(0x4000, 0x30);
+        assert_eq!(cartridge.read(0x4010), 0xff);
+    }
+}
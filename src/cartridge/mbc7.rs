@@ -0,0 +1,292 @@
+use super::read_rom;
+
+const TILT_CENTER: u16 = 0x81d0;
+const TILT_RANGE: f32 = 0x70 as f32;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EepromState {
+    Idle,
+    Command {
+        bits: u8,
+        val: u16,
+    },
+    Read {
+        bits: u8,
+        val: u16,
+    },
+    Write {
+        addr: Option<u8>,
+        bits: u8,
+        val: u16,
+    },
+}
+
+// A 93LC56 in 16-bit mode clocked by hand through bit 7 (CS), bit 6 (CLK) and bit 1 (DI);
+// bit 0 reads back DO. The 128 words live in cartridge RAM so they are saved like SRAM.
+struct Eeprom {
+    cs: bool,
+    clk: bool,
+    di: bool,
+    out: bool,
+    write_enable: bool,
+    state: EepromState,
+}
+
+impl Eeprom {
+    fn new() -> Self {
+        Self {
+            cs: false,
+            clk: false,
+            di: false,
+            out: true,
+            write_enable: false,
+            state: EepromState::Idle,
+        }
+    }
+    fn read(&self) -> u8 {
+        (self.cs as u8) << 7 | (self.clk as u8) << 6 | (self.di as u8) << 1 | self.out as u8
+    }
+    fn write(&mut self, data: &mut [u8], val: u8) {
+        let cs = val & 0x80 > 0;
+        let clk = val & 0x40 > 0;
+        self.di = val & 0x02 > 0;
+        if !cs {
+            self.state = EepromState::Idle;
+            self.out = true;
+        } else if clk && !self.clk {
+            self.clock(data);
+        }
+        self.cs = cs;
+        self.clk = clk;
+    }
+    fn store(&self, data: &mut [u8], addr: u8, val: u16) {
+        if self.write_enable {
+            let i = (addr as usize & 0x7f) * 2;
+            data[i..i + 2].copy_from_slice(&val.to_le_bytes());
+        }
+    }
+    fn clock(&mut self, data: &mut [u8]) {
+        let di = self.di as u16;
+        self.state = match self.state {
+            EepromState::Idle if di > 0 => EepromState::Command { bits: 0, val: 0 },
+            EepromState::Idle => EepromState::Idle,
+            EepromState::Command { bits: 9, val } => self.execute(data, (val << 1) | di),
+            EepromState::Command { bits, val } => EepromState::Command {
+                bits: bits + 1,
+                val: (val << 1) | di,
+            },
+            EepromState::Read { bits: 16, .. } => EepromState::Idle,
+            EepromState::Read { bits, val } => {
+                self.out = val & (0x8000 >> bits) > 0;
+                EepromState::Read {
+                    bits: bits + 1,
+                    val,
+                }
+            }
+            EepromState::Write {
+                addr,
+                bits: 15,
+                val,
+            } => {
+                let val = (val << 1) | di;
+                match addr {
+                    Some(addr) => self.store(data, addr, val),
+                    None => (0..0x80).for_each(|addr| self.store(data, addr, val)),
+                }
+                EepromState::Idle
+            }
+            EepromState::Write { addr, bits, val } => EepromState::Write {
+                addr,
+                bits: bits + 1,
+                val: (val << 1) | di,
+            },
+        };
+    }
+    fn execute(&mut self, data: &mut [u8], command: u16) -> EepromState {
+        let addr = command as u8;
+        match (command >> 8) & 0x03 {
+            0b10 => {
+                let i = (addr as usize & 0x7f) * 2;
+                self.out = false;
+                EepromState::Read {
+                    bits: 0,
+                    val: u16::from_le_bytes([data[i], data[i + 1]]),
+                }
+            }
+            0b01 => EepromState::Write {
+                addr: Some(addr),
+                bits: 0,
+                val: 0,
+            },
+            0b11 => {
+                self.store(data, addr, 0xffff);
+                EepromState::Idle
+            }
+            _ => match addr >> 6 {
+                0b11 => {
+                    self.write_enable = true;
+                    EepromState::Idle
+                }
+                0b00 => {
+                    self.write_enable = false;
+                    EepromState::Idle
+                }
+                0b10 => {
+                    (0..0x80).for_each(|addr| self.store(data, addr, 0xffff));
+                    EepromState::Idle
+                }
+                _ => EepromState::Write {
+                    addr: None,
+                    bits: 0,
+                    val: 0,
+                },
+            },
+        }
+    }
+}
+
+pub struct Mbc7 {
+    ram_enable1: bool,
+    ram_enable2: bool,
+    rom_bank: u8,
+    tilt: (u16, u16),
+    latched: (u16, u16),
+    erased: bool,
+    eeprom: Eeprom,
+}
+
+impl Mbc7 {
+    pub fn new() -> Self {
+        Self {
+            ram_enable1: false,
+            ram_enable2: false,
+            rom_bank: 1,
+            tilt: (TILT_CENTER, TILT_CENTER),
+            latched: (0x8000, 0x8000),
+            erased: false,
+            eeprom: Eeprom::new(),
+        }
+    }
+    // Tilt is given per axis in the range -1.0..=1.0, roughly one g at either end.
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        let axis = |v: f32| (TILT_CENTER as f32 + v.clamp(-1.0, 1.0) * TILT_RANGE) as u16;
+        self.tilt = (axis(x), axis(y));
+    }
+    pub fn read(&self, rom: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3fff => read_rom(rom, 0, addr),
+            0x4000..=0x7fff => read_rom(rom, self.rom_bank as usize, addr),
+            0xa000..=0xafff if self.ram_enable1 && self.ram_enable2 => match (addr >> 4) & 0x0f {
+                0x2 => self.latched.0 as u8,
+                0x3 => (self.latched.0 >> 8) as u8,
+                0x4 => self.latched.1 as u8,
+                0x5 => (self.latched.1 >> 8) as u8,
+                0x6 => 0x00,
+                0x8 => self.eeprom.read(),
+                _ => 0xff,
+            },
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, ram: &mut [u8], addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_enable1 = val == 0x0a,
+            0x2000..=0x3fff => self.rom_bank = val & 0x7f,
+            0x4000..=0x5fff => self.ram_enable2 = val == 0x40,
+            0xa000..=0xafff if self.ram_enable1 && self.ram_enable2 => match (addr >> 4) & 0x0f {
+                0x0 if val == 0x55 => {
+                    self.latched = (0x8000, 0x8000);
+                    self.erased = true;
+                }
+                0x1 if val == 0xaa && self.erased => {
+                    self.latched = self.tilt;
+                    self.erased = false;
+                }
+                0x8 => self.eeprom.write(ram, val),
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cartridge::{Cartridge, tests};
+
+    fn mbc7_cartridge() -> Cartridge {
+        let rom = tests::build_rom(0x22, 0x00, 0x00);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x0000, 0x0a);
+        cartridge.write(0x4000, 0x40);
+        cartridge
+    }
+
+    fn send(cartridge: &mut Cartridge, bits: u32, len: u8) -> u16 {
+        let mut out = 0;
+        for i in (0..len).rev() {
+            let di = ((bits >> i) as u8 & 0x01) << 1;
+            cartridge.write(0xa080, 0x80 | di);
+            cartridge.write(0xa080, 0xc0 | di);
+            out = (out << 1) | (cartridge.read(0xa080) & 0x01) as u16;
+        }
+        out
+    }
+
+    fn command(op: u32, addr: u32) -> u32 {
+        0x400 | op << 8 | addr
+    }
+
+    fn finish(cartridge: &mut Cartridge) {
+        cartridge.write(0xa080, 0x00);
+    }
+
+    #[test]
+    fn test_mbc7_tilt() {
+        let mut cartridge = mbc7_cartridge();
+        cartridge.set_tilt(1.0, -1.0);
+        cartridge.write(0xa000, 0x55);
+        assert_eq!(cartridge.read(0xa020), 0x00);
+        assert_eq!(cartridge.read(0xa030), 0x80);
+        cartridge.write(0xa010, 0xaa);
+        assert_eq!(cartridge.read(0xa020), 0x40);
+        assert_eq!(cartridge.read(0xa030), 0x82);
+        assert_eq!(cartridge.read(0xa040), 0x60);
+        assert_eq!(cartridge.read(0xa050), 0x81);
+        cartridge.set_tilt(0.0, 0.0);
+        cartridge.write(0xa010, 0xaa);
+        assert_eq!(cartridge.read(0xa020), 0x40);
+    }
+
+    #[test]
+    fn test_mbc7_disabled() {
+        let rom = tests::build_rom(0x22, 0x00, 0x00);
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x0000, 0x0a);
+        assert_eq!(cartridge.read(0xa060), 0xff);
+        cartridge.write(0x4000, 0x40);
+        assert_eq!(cartridge.read(0xa060), 0x00);
+    }
+
+    #[test]
+    fn test_mbc7_eeprom() {
+        let mut cartridge = mbc7_cartridge();
+        send(&mut cartridge, command(0b00, 0xc0), 11);
+        finish(&mut cartridge);
+        send(&mut cartridge, command(0b01, 0x05), 11);
+        send(&mut cartridge, 0xbeef, 16);
+        finish(&mut cartridge);
+        send(&mut cartridge, command(0b10, 0x05), 11);
+        assert_eq!(send(&mut cartridge, 0, 16), 0xbeef);
+        finish(&mut cartridge);
+        assert_eq!(cartridge.save_data()[10..12], [0xef, 0xbe]);
+
+        send(&mut cartridge, command(0b00, 0x00), 11);
+        finish(&mut cartridge);
+        send(&mut cartridge, command(0b11, 0x05), 11);
+        finish(&mut cartridge);
+        send(&mut cartridge, command(0b10, 0x05), 11);
+        assert_eq!(send(&mut cartridge, 0, 16), 0xbeef);
+        finish(&mut cartridge);
+    }
+}
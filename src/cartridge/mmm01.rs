@@ -0,0 +1,102 @@
+use super::{header_checksum, read_ram, read_rom, write_ram};
+
+// The menu lives in the last 32 KiB of the image and is mapped at 0x0000-0x7fff until it
+// selects a game by setting bit 6 of the 0x0000-0x1fff register. From then on the outer
+// bank chosen by the menu is the game's bank 0 and the rest behaves like MBC1.
+pub struct Mmm01 {
+    mapped: bool,
+    ram_enable: bool,
+    base: u8,
+    rom_bank: u8,
+    ram_bank: u8,
+}
+
+impl Mmm01 {
+    pub fn new() -> Self {
+        Self {
+            mapped: false,
+            ram_enable: false,
+            base: 0,
+            rom_bank: 1,
+            ram_bank: 0,
+        }
+    }
+    pub fn read(&self, rom: &[u8], ram: &[u8], addr: u16) -> u8 {
+        let menu = rom.len() / 0x4000 - 2;
+        match addr {
+            0x0000..=0x7fff if !self.mapped => read_rom(rom, menu + (addr >> 14) as usize, addr),
+            0x0000..=0x3fff => read_rom(rom, self.base as usize, addr),
+            0x4000..=0x7fff => read_rom(rom, self.base as usize + self.rom_bank as usize, addr),
+            0xa000..=0xbfff if self.ram_enable => read_ram(ram, self.ram_bank as usize, addr),
+            _ => 0xff,
+        }
+    }
+    pub fn write(&mut self, ram: &mut [u8], addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1fff => {
+                self.ram_enable = val & 0x0f == 0x0a;
+                if val & 0x40 > 0 {
+                    self.mapped = true;
+                }
+            }
+            0x2000..=0x3fff if !self.mapped => self.base = val,
+            0x2000..=0x3fff => self.rom_bank = if val & 0x1f == 0 { 1 } else { val & 0x1f },
+            0x4000..=0x5fff => self.ram_bank = val & 0x03,
+            0xa000..=0xbfff if self.ram_enable => write_ram(ram, self.ram_bank as usize, addr, val),
+            _ => (),
+        }
+    }
+}
+
+// Returns the offset of the menu header when the image is an MMM01 multi-game cart.
+pub fn menu_offset(rom: &[u8]) -> Option<usize> {
+    if rom.len() < 0x10000 || !rom.len().is_multiple_of(0x4000) {
+        return None;
+    }
+    let offset = rom.len() - 0x8000;
+    let menu = &rom[offset..];
+    match menu[0x147] {
+        0x0b..=0x0d if header_checksum(menu) == menu[0x14d] => Some(offset),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cartridge::{Cartridge, tests};
+
+    fn mmm01_rom() -> Vec<u8> {
+        let mut rom = vec![0; 0x20000];
+        for bank in 0..8 {
+            rom[bank * 0x4000 + 0x3fff] = bank as u8;
+        }
+        let menu = tests::build_rom(0x0d, 0x02, 0x03);
+        rom[0x18134..0x18150].copy_from_slice(&menu[0x134..0x150]);
+        tests::fix_checksums_at(&mut rom, 0x18000);
+        rom
+    }
+
+    #[test]
+    fn test_mmm01_menu() {
+        let rom = mmm01_rom();
+        let cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        assert_eq!(cartridge.header().cartridge_type, 0x0d);
+        assert_eq!(cartridge.read(0x3fff), 6);
+        assert_eq!(cartridge.read(0x7fff), 7);
+    }
+
+    #[test]
+    fn test_mmm01_game() {
+        let rom = mmm01_rom();
+        let mut cartridge = Cartridge::new(rom.into_boxed_slice()).unwrap();
+        cartridge.write(0x2000, 0x02);
+        cartridge.write(0x0000, 0x4a);
+        assert_eq!(cartridge.read(0x3fff), 2);
+        assert_eq!(cartridge.read(0x7fff), 3);
+        cartridge.write(0x2000, 0x02);
+        assert_eq!(cartridge.read(0x3fff), 2);
+        assert_eq!(cartridge.read(0x7fff), 4);
+        cartridge.write(0xa000, 0x12);
+        assert_eq!(cartridge.read(0xa000), 0x12);
+    }
+}
@@ -1,12 +1,351 @@
+use crate::peripherals;
 use crate::registers;
 
-#[derive(Default)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Step {
+    pub step: u8,
+    pub val8: u8,
+    pub val16: u16,
+}
+
+// Routines that can be in flight at the same time keep their state in separate slots:
+// an instruction drives a stack helper or an operand, and an operand drives Imm8.
+// Each call is one M-cycle with at most one bus access. A routine hands back its result
+// on the call after its last access, so the caller carries on in that same M-cycle.
+#[derive(Clone, Copy, Debug, Default)]
 pub struct Ctx {
     pub opcode: u8,
     pub cb: bool,
+    pub int: bool,
+    pub fetched: bool,
+    pub ime: bool,
+    pub ime_delay: bool,
+    pub halt: bool,
+    pub halt_bug: bool,
+    pub stop: bool,
+    pub locked: bool,
+    pub inst: Step,
+    pub stack: Step,
+    pub operand: Step,
+    pub imm: Step,
 }
 
+#[derive(Clone, Default)]
 pub struct Cpu {
     pub regs: registers::Registers,
     pub ctx: Ctx,
 }
+
+impl Cpu {
+    pub fn emulate_cycle(&mut self, bus: &mut peripherals::Peripherals) {
+        self.ctx.fetched = false;
+        if self.ctx.locked {
+            return;
+        }
+        if self.ctx.int {
+            self.call_isr(bus);
+        } else {
+            self.decode(bus);
+        }
+    }
+    // The vector is picked between the two pushes, so a high byte pushed onto IE can cancel
+    // the dispatch but the low byte lands too late to change it.
+    fn call_isr(&mut self, bus: &mut peripherals::Peripherals) {
+        match self.ctx.inst.step {
+            0 => {
+                self.ctx.inst.step = 1;
+            }
+            1 => {
+                let [lo, hi] = u16::to_le_bytes(self.regs.pc);
+                self.regs.sp = self.regs.sp.wrapping_sub(1);
+                bus.write(self.regs.sp, hi);
+                self.ctx.inst.val8 = lo;
+                let irq = bus.interrupts.irq();
+                self.ctx.inst.val16 = if irq == 0 {
+                    0x0000
+                } else {
+                    let n = irq.trailing_zeros() as u16;
+                    bus.interrupts.int_flags &= !(1 << n);
+                    0x0040 + 8 * n
+                };
+                self.ctx.inst.step = 2;
+            }
+            2 => {
+                self.regs.sp = self.regs.sp.wrapping_sub(1);
+                bus.write(self.regs.sp, self.ctx.inst.val8);
+                self.ctx.inst.step = 3;
+            }
+            3 => {
+                self.regs.pc = self.ctx.inst.val16;
+                self.ctx.ime = false;
+                self.ctx.inst.step = 4;
+            }
+            4 => {
+                self.ctx.inst.step = 0;
+                self.fetch(bus);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interrupts;
+    #[test]
+    fn test_interrupt_dispatch() {
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        cpu.ctx.ime = true;
+        peripherals.write(0xffff, interrupts::VBLANK | interrupts::TIMER);
+        peripherals.write(0xff0f, interrupts::VBLANK | interrupts::TIMER);
+        cpu.fetch(&peripherals);
+        assert!(cpu.ctx.int);
+        for _ in 0..5 {
+            cpu.emulate_cycle(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0x0041);
+        assert_eq!(cpu.regs.sp, 0xfffc);
+        assert_eq!(peripherals.read(0xfffd), 0xc0);
+        assert_eq!(peripherals.read(0xfffc), 0x00);
+        assert!(!cpu.ctx.ime);
+        assert_eq!(peripherals.interrupts.int_flags, interrupts::TIMER);
+    }
+
+    #[test]
+    fn test_interrupt_priority() {
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        cpu.ctx.ime = true;
+        peripherals.write(0xffff, 0x1f);
+        peripherals.write(
+            0xff0f,
+            interrupts::JOYPAD | interrupts::SERIAL | interrupts::STAT,
+        );
+        cpu.fetch(&peripherals);
+        for _ in 0..5 {
+            cpu.emulate_cycle(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0x0049);
+        assert_eq!(
+            peripherals.interrupts.int_flags,
+            interrupts::JOYPAD | interrupts::SERIAL
+        );
+    }
+
+    #[test]
+    fn test_interrupt_ime_disabled() {
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        peripherals.write(0xffff, interrupts::VBLANK);
+        peripherals.write(0xff0f, interrupts::VBLANK);
+        cpu.fetch(&peripherals);
+        cpu.emulate_cycle(&mut peripherals);
+        assert!(!cpu.ctx.int);
+        assert_eq!(cpu.regs.pc, 0xc002);
+    }
+
+    #[test]
+    fn test_ei_delay() {
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        peripherals.write(0xc000, 0xfb);
+        peripherals.write(0xc001, 0x00);
+        peripherals.write(0xc002, 0x00);
+        peripherals.write(0xffff, interrupts::TIMER);
+        peripherals.write(0xff0f, interrupts::TIMER);
+        cpu.fetch(&peripherals);
+        cpu.emulate_cycle(&mut peripherals);
+        assert!(!cpu.ctx.int);
+        cpu.emulate_cycle(&mut peripherals);
+        assert!(cpu.ctx.int);
+        for _ in 0..5 {
+            cpu.emulate_cycle(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0x0051);
+        assert_eq!(peripherals.read(0xfffc), 0x02);
+    }
+
+    #[test]
+    fn test_reti() {
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffc;
+        peripherals.write(0xfffc, 0x10);
+        peripherals.write(0xfffd, 0xc0);
+        peripherals.write(0xc000, 0xd9);
+        cpu.fetch(&peripherals);
+        while cpu.ctx.opcode == 0xd9 {
+            cpu.emulate_cycle(&mut peripherals);
+        }
+        assert!(cpu.ctx.ime);
+        assert_eq!(cpu.regs.pc, 0xc011);
+        assert_eq!(cpu.regs.sp, 0xfffe);
+    }
+
+    #[test]
+    fn test_halt_wake_without_ime() {
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        peripherals.write(0xc000, 0x76);
+        peripherals.write(0xffff, interrupts::TIMER);
+        cpu.fetch(&peripherals);
+        for _ in 0..10 {
+            cpu.emulate_cycle(&mut peripherals);
+        }
+        assert!(cpu.ctx.halt);
+        assert_eq!(cpu.regs.pc, 0xc001);
+        peripherals.interrupts.request(interrupts::TIMER);
+        cpu.emulate_cycle(&mut peripherals);
+        assert!(!cpu.ctx.halt);
+        assert!(!cpu.ctx.int);
+        assert_eq!(cpu.regs.pc, 0xc002);
+    }
+
+    #[test]
+    fn test_halt_wake_with_ime() {
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        cpu.ctx.ime = true;
+        peripherals.write(0xc000, 0x76);
+        peripherals.write(0xffff, interrupts::JOYPAD);
+        cpu.fetch(&peripherals);
+        cpu.emulate_cycle(&mut peripherals);
+        assert!(cpu.ctx.halt);
+        peripherals.interrupts.request(interrupts::JOYPAD);
+        cpu.emulate_cycle(&mut peripherals);
+        assert!(cpu.ctx.int);
+        for _ in 0..5 {
+            cpu.emulate_cycle(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0x0061);
+        assert_eq!(peripherals.read(0xfffc), 0x01);
+    }
+
+    #[test]
+    fn test_halt_bug() {
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        peripherals.write(0xc000, 0x76);
+        peripherals.write(0xc001, 0x3c);
+        peripherals.write(0xffff, interrupts::TIMER);
+        peripherals.write(0xff0f, interrupts::TIMER);
+        cpu.fetch(&peripherals);
+        cpu.emulate_cycle(&mut peripherals);
+        assert!(!cpu.ctx.halt);
+        assert_eq!(cpu.regs.pc, 0xc001);
+        for _ in 0..2 {
+            cpu.emulate_cycle(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.a, 2);
+        assert_eq!(cpu.regs.pc, 0xc003);
+    }
+
+    #[test]
+    fn test_interrupt_cancelled_by_ie_push() {
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0x0200;
+        cpu.regs.sp = 0x0000;
+        cpu.ctx.ime = true;
+        peripherals.write(0xffff, interrupts::VBLANK);
+        peripherals.write(0xff0f, interrupts::VBLANK);
+        cpu.fetch(&peripherals);
+        for _ in 0..5 {
+            cpu.emulate_cycle(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0x0001);
+        assert_eq!(peripherals.interrupts.int_flags, interrupts::VBLANK);
+
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0x0200;
+        cpu.regs.sp = 0x0001;
+        cpu.ctx.ime = true;
+        peripherals.write(0xffff, interrupts::VBLANK);
+        peripherals.write(0xff0f, interrupts::VBLANK);
+        cpu.fetch(&peripherals);
+        for _ in 0..5 {
+            cpu.emulate_cycle(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0x0041);
+        assert_eq!(peripherals.read(0xffff), 0x00);
+        assert_eq!(peripherals.interrupts.int_flags, 0);
+    }
+
+    #[test]
+    fn test_stop_wakes_on_joypad() {
+        let mut cpu = Cpu {
+            regs: registers::Registers::default(),
+            ctx: Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        peripherals.write(0xc000, 0x10);
+        peripherals.write(0xc001, 0x00);
+        cpu.fetch(&peripherals);
+        for _ in 0..10 {
+            cpu.emulate_cycle(&mut peripherals);
+        }
+        assert!(cpu.ctx.stop);
+        assert_eq!(cpu.regs.pc, 0xc002);
+        peripherals.interrupts.request(interrupts::JOYPAD);
+        cpu.emulate_cycle(&mut peripherals);
+        assert!(!cpu.ctx.stop);
+        assert_eq!(cpu.regs.pc, 0xc003);
+    }
+}
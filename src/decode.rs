@@ -4,15 +4,516 @@ use crate::peripherals;
 
 impl cpu::Cpu {
     pub fn decode(&mut self, bus: &mut peripherals::Peripherals) {
+        if self.ctx.cb {
+            return self.cb_decode(bus);
+        }
         match self.ctx.opcode {
+            0xcb => self.cb_prefixed(bus),
+            0x07 => self.rlca(bus),
+            0x0f => self.rrca(bus),
+            0x17 => self.rla(bus),
+            0x1f => self.rra(bus),
             0x00 => self.nop(bus),
-            0x20 => self.jr_c(bus, operand::Cond::NZ),
-            0x30 => self.jr_c(bus, operand::Cond::NC),
+            0x10 => self.stop(bus),
+            0x76 => self.halt(bus),
+            0xf3 => self.di(bus),
+            0xfb => self.ei(bus),
             0x01 => self.ld16(bus, operand::Reg16::BC, operand::Imm16),
             0x11 => self.ld16(bus, operand::Reg16::DE, operand::Imm16),
             0x21 => self.ld16(bus, operand::Reg16::HL, operand::Imm16),
             0x31 => self.ld16(bus, operand::Reg16::SP, operand::Imm16),
-            _ => unimplemented!("opcode {:02x} not implemented", self.ctx.opcode),
+            0x08 => self.ld16(bus, operand::Direct16, operand::Reg16::SP),
+            0xf9 => self.ld_sp_hl(bus),
+            0x02 => self.ld(bus, operand::Indirect::BC, operand::Reg8::A),
+            0x12 => self.ld(bus, operand::Indirect::DE, operand::Reg8::A),
+            0x22 => self.ld(bus, operand::Indirect::HLI, operand::Reg8::A),
+            0x32 => self.ld(bus, operand::Indirect::HLD, operand::Reg8::A),
+            0x0a => self.ld(bus, operand::Reg8::A, operand::Indirect::BC),
+            0x1a => self.ld(bus, operand::Reg8::A, operand::Indirect::DE),
+            0x2a => self.ld(bus, operand::Reg8::A, operand::Indirect::HLI),
+            0x3a => self.ld(bus, operand::Reg8::A, operand::Indirect::HLD),
+            0xe0 => self.ld(bus, operand::Direct8::DFE, operand::Reg8::A),
+            0xf0 => self.ld(bus, operand::Reg8::A, operand::Direct8::DFE),
+            0xe2 => self.ld(bus, operand::Indirect::CFF, operand::Reg8::A),
+            0xf2 => self.ld(bus, operand::Reg8::A, operand::Indirect::CFF),
+            0xea => self.ld(bus, operand::Direct8::D, operand::Reg8::A),
+            0xfa => self.ld(bus, operand::Reg8::A, operand::Direct8::D),
+            0x06 => self.ld(bus, operand::Reg8::B, operand::Imm8),
+            0x0e => self.ld(bus, operand::Reg8::C, operand::Imm8),
+            0x16 => self.ld(bus, operand::Reg8::D, operand::Imm8),
+            0x1e => self.ld(bus, operand::Reg8::E, operand::Imm8),
+            0x26 => self.ld(bus, operand::Reg8::H, operand::Imm8),
+            0x2e => self.ld(bus, operand::Reg8::L, operand::Imm8),
+            0x36 => self.ld(bus, operand::Indirect::HL, operand::Imm8),
+            0x3e => self.ld(bus, operand::Reg8::A, operand::Imm8),
+            0x40 => self.ld(bus, operand::Reg8::B, operand::Reg8::B),
+            0x41 => self.ld(bus, operand::Reg8::B, operand::Reg8::C),
+            0x42 => self.ld(bus, operand::Reg8::B, operand::Reg8::D),
+            0x43 => self.ld(bus, operand::Reg8::B, operand::Reg8::E),
+            0x44 => self.ld(bus, operand::Reg8::B, operand::Reg8::H),
+            0x45 => self.ld(bus, operand::Reg8::B, operand::Reg8::L),
+            0x46 => self.ld(bus, operand::Reg8::B, operand::Indirect::HL),
+            0x47 => self.ld(bus, operand::Reg8::B, operand::Reg8::A),
+            0x48 => self.ld(bus, operand::Reg8::C, operand::Reg8::B),
+            0x49 => self.ld(bus, operand::Reg8::C, operand::Reg8::C),
+            0x4a => self.ld(bus, operand::Reg8::C, operand::Reg8::D),
+            0x4b => self.ld(bus, operand::Reg8::C, operand::Reg8::E),
+            0x4c => self.ld(bus, operand::Reg8::C, operand::Reg8::H),
+            0x4d => self.ld(bus, operand::Reg8::C, operand::Reg8::L),
+            0x4e => self.ld(bus, operand::Reg8::C, operand::Indirect::HL),
+            0x4f => self.ld(bus, operand::Reg8::C, operand::Reg8::A),
+            0x50 => self.ld(bus, operand::Reg8::D, operand::Reg8::B),
+            0x51 => self.ld(bus, operand::Reg8::D, operand::Reg8::C),
+            0x52 => self.ld(bus, operand::Reg8::D, operand::Reg8::D),
+            0x53 => self.ld(bus, operand::Reg8::D, operand::Reg8::E),
+            0x54 => self.ld(bus, operand::Reg8::D, operand::Reg8::H),
+            0x55 => self.ld(bus, operand::Reg8::D, operand::Reg8::L),
+            0x56 => self.ld(bus, operand::Reg8::D, operand::Indirect::HL),
+            0x57 => self.ld(bus, operand::Reg8::D, operand::Reg8::A),
+            0x58 => self.ld(bus, operand::Reg8::E, operand::Reg8::B),
+            0x59 => self.ld(bus, operand::Reg8::E, operand::Reg8::C),
+            0x5a => self.ld(bus, operand::Reg8::E, operand::Reg8::D),
+            0x5b => self.ld(bus, operand::Reg8::E, operand::Reg8::E),
+            0x5c => self.ld(bus, operand::Reg8::E, operand::Reg8::H),
+            0x5d => self.ld(bus, operand::Reg8::E, operand::Reg8::L),
+            0x5e => self.ld(bus, operand::Reg8::E, operand::Indirect::HL),
+            0x5f => self.ld(bus, operand::Reg8::E, operand::Reg8::A),
+            0x60 => self.ld(bus, operand::Reg8::H, operand::Reg8::B),
+            0x61 => self.ld(bus, operand::Reg8::H, operand::Reg8::C),
+            0x62 => self.ld(bus, operand::Reg8::H, operand::Reg8::D),
+            0x63 => self.ld(bus, operand::Reg8::H, operand::Reg8::E),
+            0x64 => self.ld(bus, operand::Reg8::H, operand::Reg8::H),
+            0x65 => self.ld(bus, operand::Reg8::H, operand::Reg8::L),
+            0x66 => self.ld(bus, operand::Reg8::H, operand::Indirect::HL),
+            0x67 => self.ld(bus, operand::Reg8::H, operand::Reg8::A),
+            0x68 => self.ld(bus, operand::Reg8::L, operand::Reg8::B),
+            0x69 => self.ld(bus, operand::Reg8::L, operand::Reg8::C),
+            0x6a => self.ld(bus, operand::Reg8::L, operand::Reg8::D),
+            0x6b => self.ld(bus, operand::Reg8::L, operand::Reg8::E),
+            0x6c => self.ld(bus, operand::Reg8::L, operand::Reg8::H),
+            0x6d => self.ld(bus, operand::Reg8::L, operand::Reg8::L),
+            0x6e => self.ld(bus, operand::Reg8::L, operand::Indirect::HL),
+            0x6f => self.ld(bus, operand::Reg8::L, operand::Reg8::A),
+            0x70 => self.ld(bus, operand::Indirect::HL, operand::Reg8::B),
+            0x71 => self.ld(bus, operand::Indirect::HL, operand::Reg8::C),
+            0x72 => self.ld(bus, operand::Indirect::HL, operand::Reg8::D),
+            0x73 => self.ld(bus, operand::Indirect::HL, operand::Reg8::E),
+            0x74 => self.ld(bus, operand::Indirect::HL, operand::Reg8::H),
+            0x75 => self.ld(bus, operand::Indirect::HL, operand::Reg8::L),
+            0x77 => self.ld(bus, operand::Indirect::HL, operand::Reg8::A),
+            0x78 => self.ld(bus, operand::Reg8::A, operand::Reg8::B),
+            0x79 => self.ld(bus, operand::Reg8::A, operand::Reg8::C),
+            0x7a => self.ld(bus, operand::Reg8::A, operand::Reg8::D),
+            0x7b => self.ld(bus, operand::Reg8::A, operand::Reg8::E),
+            0x7c => self.ld(bus, operand::Reg8::A, operand::Reg8::H),
+            0x7d => self.ld(bus, operand::Reg8::A, operand::Reg8::L),
+            0x7e => self.ld(bus, operand::Reg8::A, operand::Indirect::HL),
+            0x7f => self.ld(bus, operand::Reg8::A, operand::Reg8::A),
+            0x04 => self.inc(bus, operand::Reg8::B),
+            0x05 => self.dec(bus, operand::Reg8::B),
+            0x0c => self.inc(bus, operand::Reg8::C),
+            0x0d => self.dec(bus, operand::Reg8::C),
+            0x14 => self.inc(bus, operand::Reg8::D),
+            0x15 => self.dec(bus, operand::Reg8::D),
+            0x1c => self.inc(bus, operand::Reg8::E),
+            0x1d => self.dec(bus, operand::Reg8::E),
+            0x24 => self.inc(bus, operand::Reg8::H),
+            0x25 => self.dec(bus, operand::Reg8::H),
+            0x2c => self.inc(bus, operand::Reg8::L),
+            0x2d => self.dec(bus, operand::Reg8::L),
+            0x34 => self.inc(bus, operand::Indirect::HL),
+            0x35 => self.dec(bus, operand::Indirect::HL),
+            0x3c => self.inc(bus, operand::Reg8::A),
+            0x3d => self.dec(bus, operand::Reg8::A),
+            0x03 => self.inc16(bus, operand::Reg16::BC),
+            0x0b => self.dec16(bus, operand::Reg16::BC),
+            0x13 => self.inc16(bus, operand::Reg16::DE),
+            0x1b => self.dec16(bus, operand::Reg16::DE),
+            0x23 => self.inc16(bus, operand::Reg16::HL),
+            0x2b => self.dec16(bus, operand::Reg16::HL),
+            0x33 => self.inc16(bus, operand::Reg16::SP),
+            0x3b => self.dec16(bus, operand::Reg16::SP),
+            0x80 => self.add(bus, operand::Reg8::B),
+            0x81 => self.add(bus, operand::Reg8::C),
+            0x82 => self.add(bus, operand::Reg8::D),
+            0x83 => self.add(bus, operand::Reg8::E),
+            0x84 => self.add(bus, operand::Reg8::H),
+            0x85 => self.add(bus, operand::Reg8::L),
+            0x86 => self.add(bus, operand::Indirect::HL),
+            0x87 => self.add(bus, operand::Reg8::A),
+            0xc6 => self.add(bus, operand::Imm8),
+            0x88 => self.adc(bus, operand::Reg8::B),
+            0x89 => self.adc(bus, operand::Reg8::C),
+            0x8a => self.adc(bus, operand::Reg8::D),
+            0x8b => self.adc(bus, operand::Reg8::E),
+            0x8c => self.adc(bus, operand::Reg8::H),
+            0x8d => self.adc(bus, operand::Reg8::L),
+            0x8e => self.adc(bus, operand::Indirect::HL),
+            0x8f => self.adc(bus, operand::Reg8::A),
+            0xce => self.adc(bus, operand::Imm8),
+            0x90 => self.sub(bus, operand::Reg8::B),
+            0x91 => self.sub(bus, operand::Reg8::C),
+            0x92 => self.sub(bus, operand::Reg8::D),
+            0x93 => self.sub(bus, operand::Reg8::E),
+            0x94 => self.sub(bus, operand::Reg8::H),
+            0x95 => self.sub(bus, operand::Reg8::L),
+            0x96 => self.sub(bus, operand::Indirect::HL),
+            0x97 => self.sub(bus, operand::Reg8::A),
+            0xd6 => self.sub(bus, operand::Imm8),
+            0x98 => self.sbc(bus, operand::Reg8::B),
+            0x99 => self.sbc(bus, operand::Reg8::C),
+            0x9a => self.sbc(bus, operand::Reg8::D),
+            0x9b => self.sbc(bus, operand::Reg8::E),
+            0x9c => self.sbc(bus, operand::Reg8::H),
+            0x9d => self.sbc(bus, operand::Reg8::L),
+            0x9e => self.sbc(bus, operand::Indirect::HL),
+            0x9f => self.sbc(bus, operand::Reg8::A),
+            0xde => self.sbc(bus, operand::Imm8),
+            0xa0 => self.and(bus, operand::Reg8::B),
+            0xa1 => self.and(bus, operand::Reg8::C),
+            0xa2 => self.and(bus, operand::Reg8::D),
+            0xa3 => self.and(bus, operand::Reg8::E),
+            0xa4 => self.and(bus, operand::Reg8::H),
+            0xa5 => self.and(bus, operand::Reg8::L),
+            0xa6 => self.and(bus, operand::Indirect::HL),
+            0xa7 => self.and(bus, operand::Reg8::A),
+            0xe6 => self.and(bus, operand::Imm8),
+            0xa8 => self.xor(bus, operand::Reg8::B),
+            0xa9 => self.xor(bus, operand::Reg8::C),
+            0xaa => self.xor(bus, operand::Reg8::D),
+            0xab => self.xor(bus, operand::Reg8::E),
+            0xac => self.xor(bus, operand::Reg8::H),
+            0xad => self.xor(bus, operand::Reg8::L),
+            0xae => self.xor(bus, operand::Indirect::HL),
+            0xaf => self.xor(bus, operand::Reg8::A),
+            0xee => self.xor(bus, operand::Imm8),
+            0xb0 => self.or(bus, operand::Reg8::B),
+            0xb1 => self.or(bus, operand::Reg8::C),
+            0xb2 => self.or(bus, operand::Reg8::D),
+            0xb3 => self.or(bus, operand::Reg8::E),
+            0xb4 => self.or(bus, operand::Reg8::H),
+            0xb5 => self.or(bus, operand::Reg8::L),
+            0xb6 => self.or(bus, operand::Indirect::HL),
+            0xb7 => self.or(bus, operand::Reg8::A),
+            0xf6 => self.or(bus, operand::Imm8),
+            0x09 => self.add_hl(bus, operand::Reg16::BC),
+            0x19 => self.add_hl(bus, operand::Reg16::DE),
+            0x29 => self.add_hl(bus, operand::Reg16::HL),
+            0x39 => self.add_hl(bus, operand::Reg16::SP),
+            0xe8 => self.add_sp(bus),
+            0xf8 => self.ld_hl_sp(bus),
+            0x27 => self.daa(bus),
+            0x2f => self.cpl(bus),
+            0x37 => self.scf(bus),
+            0x3f => self.ccf(bus),
+            0xb8 => self.cp(bus, operand::Reg8::B),
+            0xb9 => self.cp(bus, operand::Reg8::C),
+            0xba => self.cp(bus, operand::Reg8::D),
+            0xbb => self.cp(bus, operand::Reg8::E),
+            0xbc => self.cp(bus, operand::Reg8::H),
+            0xbd => self.cp(bus, operand::Reg8::L),
+            0xbe => self.cp(bus, operand::Indirect::HL),
+            0xbf => self.cp(bus, operand::Reg8::A),
+            0xfe => self.cp(bus, operand::Imm8),
+            0x18 => self.jr(bus),
+            0x20 => self.jr_c(bus, operand::Cond::NZ),
+            0x28 => self.jr_c(bus, operand::Cond::Z),
+            0x30 => self.jr_c(bus, operand::Cond::NC),
+            0x38 => self.jr_c(bus, operand::Cond::C),
+            0xc3 => self.jp(bus),
+            0xe9 => self.jp_hl(bus),
+            0xc2 => self.jp_c(bus, operand::Cond::NZ),
+            0xca => self.jp_c(bus, operand::Cond::Z),
+            0xd2 => self.jp_c(bus, operand::Cond::NC),
+            0xda => self.jp_c(bus, operand::Cond::C),
+            0xcd => self.call(bus),
+            0xc4 => self.call_c(bus, operand::Cond::NZ),
+            0xcc => self.call_c(bus, operand::Cond::Z),
+            0xd4 => self.call_c(bus, operand::Cond::NC),
+            0xdc => self.call_c(bus, operand::Cond::C),
+            0xc9 => self.ret(bus),
+            0xd9 => self.reti(bus),
+            0xc0 => self.ret_c(bus, operand::Cond::NZ),
+            0xc8 => self.ret_c(bus, operand::Cond::Z),
+            0xd0 => self.ret_c(bus, operand::Cond::NC),
+            0xd8 => self.ret_c(bus, operand::Cond::C),
+            0xc1 => self.pop(bus, operand::Reg16::BC),
+            0xc5 => self.push(bus, operand::Reg16::BC),
+            0xd1 => self.pop(bus, operand::Reg16::DE),
+            0xd5 => self.push(bus, operand::Reg16::DE),
+            0xe1 => self.pop(bus, operand::Reg16::HL),
+            0xe5 => self.push(bus, operand::Reg16::HL),
+            0xf1 => self.pop(bus, operand::Reg16::AF),
+            0xf5 => self.push(bus, operand::Reg16::AF),
+            0xc7 => self.rst(bus, 0x00),
+            0xcf => self.rst(bus, 0x08),
+            0xd7 => self.rst(bus, 0x10),
+            0xdf => self.rst(bus, 0x18),
+            0xe7 => self.rst(bus, 0x20),
+            0xef => self.rst(bus, 0x28),
+            0xf7 => self.rst(bus, 0x30),
+            0xff => self.rst(bus, 0x38),
+            _ => self.illegal(),
+        }
+    }
+    pub fn cb_decode(&mut self, bus: &mut peripherals::Peripherals) {
+        match self.ctx.opcode {
+            0x00 => self.rlc(bus, operand::Reg8::B),
+            0x01 => self.rlc(bus, operand::Reg8::C),
+            0x02 => self.rlc(bus, operand::Reg8::D),
+            0x03 => self.rlc(bus, operand::Reg8::E),
+            0x04 => self.rlc(bus, operand::Reg8::H),
+            0x05 => self.rlc(bus, operand::Reg8::L),
+            0x06 => self.rlc(bus, operand::Indirect::HL),
+            0x07 => self.rlc(bus, operand::Reg8::A),
+            0x08 => self.rrc(bus, operand::Reg8::B),
+            0x09 => self.rrc(bus, operand::Reg8::C),
+            0x0a => self.rrc(bus, operand::Reg8::D),
+            0x0b => self.rrc(bus, operand::Reg8::E),
+            0x0c => self.rrc(bus, operand::Reg8::H),
+            0x0d => self.rrc(bus, operand::Reg8::L),
+            0x0e => self.rrc(bus, operand::Indirect::HL),
+            0x0f => self.rrc(bus, operand::Reg8::A),
+            0x10 => self.rl(bus, operand::Reg8::B),
+            0x11 => self.rl(bus, operand::Reg8::C),
+            0x12 => self.rl(bus, operand::Reg8::D),
+            0x13 => self.rl(bus, operand::Reg8::E),
+            0x14 => self.rl(bus, operand::Reg8::H),
+            0x15 => self.rl(bus, operand::Reg8::L),
+            0x16 => self.rl(bus, operand::Indirect::HL),
+            0x17 => self.rl(bus, operand::Reg8::A),
+            0x18 => self.rr(bus, operand::Reg8::B),
+            0x19 => self.rr(bus, operand::Reg8::C),
+            0x1a => self.rr(bus, operand::Reg8::D),
+            0x1b => self.rr(bus, operand::Reg8::E),
+            0x1c => self.rr(bus, operand::Reg8::H),
+            0x1d => self.rr(bus, operand::Reg8::L),
+            0x1e => self.rr(bus, operand::Indirect::HL),
+            0x1f => self.rr(bus, operand::Reg8::A),
+            0x20 => self.sla(bus, operand::Reg8::B),
+            0x21 => self.sla(bus, operand::Reg8::C),
+            0x22 => self.sla(bus, operand::Reg8::D),
+            0x23 => self.sla(bus, operand::Reg8::E),
+            0x24 => self.sla(bus, operand::Reg8::H),
+            0x25 => self.sla(bus, operand::Reg8::L),
+            0x26 => self.sla(bus, operand::Indirect::HL),
+            0x27 => self.sla(bus, operand::Reg8::A),
+            0x28 => self.sra(bus, operand::Reg8::B),
+            0x29 => self.sra(bus, operand::Reg8::C),
+            0x2a => self.sra(bus, operand::Reg8::D),
+            0x2b => self.sra(bus, operand::Reg8::E),
+            0x2c => self.sra(bus, operand::Reg8::H),
+            0x2d => self.sra(bus, operand::Reg8::L),
+            0x2e => self.sra(bus, operand::Indirect::HL),
+            0x2f => self.sra(bus, operand::Reg8::A),
+            0x30 => self.swap(bus, operand::Reg8::B),
+            0x31 => self.swap(bus, operand::Reg8::C),
+            0x32 => self.swap(bus, operand::Reg8::D),
+            0x33 => self.swap(bus, operand::Reg8::E),
+            0x34 => self.swap(bus, operand::Reg8::H),
+            0x35 => self.swap(bus, operand::Reg8::L),
+            0x36 => self.swap(bus, operand::Indirect::HL),
+            0x37 => self.swap(bus, operand::Reg8::A),
+            0x38 => self.srl(bus, operand::Reg8::B),
+            0x39 => self.srl(bus, operand::Reg8::C),
+            0x3a => self.srl(bus, operand::Reg8::D),
+            0x3b => self.srl(bus, operand::Reg8::E),
+            0x3c => self.srl(bus, operand::Reg8::H),
+            0x3d => self.srl(bus, operand::Reg8::L),
+            0x3e => self.srl(bus, operand::Indirect::HL),
+            0x3f => self.srl(bus, operand::Reg8::A),
+            0x40 => self.bit(bus, 0, operand::Reg8::B),
+            0x41 => self.bit(bus, 0, operand::Reg8::C),
+            0x42 => self.bit(bus, 0, operand::Reg8::D),
+            0x43 => self.bit(bus, 0, operand::Reg8::E),
+            0x44 => self.bit(bus, 0, operand::Reg8::H),
+            0x45 => self.bit(bus, 0, operand::Reg8::L),
+            0x46 => self.bit(bus, 0, operand::Indirect::HL),
+            0x47 => self.bit(bus, 0, operand::Reg8::A),
+            0x48 => self.bit(bus, 1, operand::Reg8::B),
+            0x49 => self.bit(bus, 1, operand::Reg8::C),
+            0x4a => self.bit(bus, 1, operand::Reg8::D),
+            0x4b => self.bit(bus, 1, operand::Reg8::E),
+            0x4c => self.bit(bus, 1, operand::Reg8::H),
+            0x4d => self.bit(bus, 1, operand::Reg8::L),
+            0x4e => self.bit(bus, 1, operand::Indirect::HL),
+            0x4f => self.bit(bus, 1, operand::Reg8::A),
+            0x50 => self.bit(bus, 2, operand::Reg8::B),
+            0x51 => self.bit(bus, 2, operand::Reg8::C),
+            0x52 => self.bit(bus, 2, operand::Reg8::D),
+            0x53 => self.bit(bus, 2, operand::Reg8::E),
+            0x54 => self.bit(bus, 2, operand::Reg8::H),
+            0x55 => self.bit(bus, 2, operand::Reg8::L),
+            0x56 => self.bit(bus, 2, operand::Indirect::HL),
+            0x57 => self.bit(bus, 2, operand::Reg8::A),
+            0x58 => self.bit(bus, 3, operand::Reg8::B),
+            0x59 => self.bit(bus, 3, operand::Reg8::C),
+            0x5a => self.bit(bus, 3, operand::Reg8::D),
+            0x5b => self.bit(bus, 3, operand::Reg8::E),
+            0x5c => self.bit(bus, 3, operand::Reg8::H),
+            0x5d => self.bit(bus, 3, operand::Reg8::L),
+            0x5e => self.bit(bus, 3, operand::Indirect::HL),
+            0x5f => self.bit(bus, 3, operand::Reg8::A),
+            0x60 => self.bit(bus, 4, operand::Reg8::B),
+            0x61 => self.bit(bus, 4, operand::Reg8::C),
+            0x62 => self.bit(bus, 4, operand::Reg8::D),
+            0x63 => self.bit(bus, 4, operand::Reg8::E),
+            0x64 => self.bit(bus, 4, operand::Reg8::H),
+            0x65 => self.bit(bus, 4, operand::Reg8::L),
+            0x66 => self.bit(bus, 4, operand::Indirect::HL),
+            0x67 => self.bit(bus, 4, operand::Reg8::A),
+            0x68 => self.bit(bus, 5, operand::Reg8::B),
+            0x69 => self.bit(bus, 5, operand::Reg8::C),
+            0x6a => self.bit(bus, 5, operand::Reg8::D),
+            0x6b => self.bit(bus, 5, operand::Reg8::E),
+            0x6c => self.bit(bus, 5, operand::Reg8::H),
+            0x6d => self.bit(bus, 5, operand::Reg8::L),
+            0x6e => self.bit(bus, 5, operand::Indirect::HL),
+            0x6f => self.bit(bus, 5, operand::Reg8::A),
+            0x70 => self.bit(bus, 6, operand::Reg8::B),
+            0x71 => self.bit(bus, 6, operand::Reg8::C),
+            0x72 => self.bit(bus, 6, operand::Reg8::D),
+            0x73 => self.bit(bus, 6, operand::Reg8::E),
+            0x74 => self.bit(bus, 6, operand::Reg8::H),
+            0x75 => self.bit(bus, 6, operand::Reg8::L),
+            0x76 => self.bit(bus, 6, operand::Indirect::HL),
+            0x77 => self.bit(bus, 6, operand::Reg8::A),
+            0x78 => self.bit(bus, 7, operand::Reg8::B),
+            0x79 => self.bit(bus, 7, operand::Reg8::C),
+            0x7a => self.bit(bus, 7, operand::Reg8::D),
+            0x7b => self.bit(bus, 7, operand::Reg8::E),
+            0x7c => self.bit(bus, 7, operand::Reg8::H),
+            0x7d => self.bit(bus, 7, operand::Reg8::L),
+            0x7e => self.bit(bus, 7, operand::Indirect::HL),
+            0x7f => self.bit(bus, 7, operand::Reg8::A),
+            0x80 => self.res(bus, 0, operand::Reg8::B),
+            0x81 => self.res(bus, 0, operand::Reg8::C),
+            0x82 => self.res(bus, 0, operand::Reg8::D),
+            0x83 => self.res(bus, 0, operand::Reg8::E),
+            0x84 => self.res(bus, 0, operand::Reg8::H),
+            0x85 => self.res(bus, 0, operand::Reg8::L),
+            0x86 => self.res(bus, 0, operand::Indirect::HL),
+            0x87 => self.res(bus, 0, operand::Reg8::A),
+            0x88 => self.res(bus, 1, operand::Reg8::B),
+            0x89 => self.res(bus, 1, operand::Reg8::C),
+            0x8a => self.res(bus, 1, operand::Reg8::D),
+            0x8b => self.res(bus, 1, operand::Reg8::E),
+            0x8c => self.res(bus, 1, operand::Reg8::H),
+            0x8d => self.res(bus, 1, operand::Reg8::L),
+            0x8e => self.res(bus, 1, operand::Indirect::HL),
+            0x8f => self.res(bus, 1, operand::Reg8::A),
+            0x90 => self.res(bus, 2, operand::Reg8::B),
+            0x91 => self.res(bus, 2, operand::Reg8::C),
+            0x92 => self.res(bus, 2, operand::Reg8::D),
+            0x93 => self.res(bus, 2, operand::Reg8::E),
+            0x94 => self.res(bus, 2, operand::Reg8::H),
+            0x95 => self.res(bus, 2, operand::Reg8::L),
+            0x96 => self.res(bus, 2, operand::Indirect::HL),
+            0x97 => self.res(bus, 2, operand::Reg8::A),
+            0x98 => self.res(bus, 3, operand::Reg8::B),
+            0x99 => self.res(bus, 3, operand::Reg8::C),
+            0x9a => self.res(bus, 3, operand::Reg8::D),
+            0x9b => self.res(bus, 3, operand::Reg8::E),
+            0x9c => self.res(bus, 3, operand::Reg8::H),
+            0x9d => self.res(bus, 3, operand::Reg8::L),
+            0x9e => self.res(bus, 3, operand::Indirect::HL),
+            0x9f => self.res(bus, 3, operand::Reg8::A),
+            0xa0 => self.res(bus, 4, operand::Reg8::B),
+            0xa1 => self.res(bus, 4, operand::Reg8::C),
+            0xa2 => self.res(bus, 4, operand::Reg8::D),
+            0xa3 => self.res(bus, 4, operand::Reg8::E),
+            0xa4 => self.res(bus, 4, operand::Reg8::H),
+            0xa5 => self.res(bus, 4, operand::Reg8::L),
+            0xa6 => self.res(bus, 4, operand::Indirect::HL),
+            0xa7 => self.res(bus, 4, operand::Reg8::A),
+            0xa8 => self.res(bus, 5, operand::Reg8::B),
+            0xa9 => self.res(bus, 5, operand::Reg8::C),
+            0xaa => self.res(bus, 5, operand::Reg8::D),
+            0xab => self.res(bus, 5, operand::Reg8::E),
+            0xac => self.res(bus, 5, operand::Reg8::H),
+            0xad => self.res(bus, 5, operand::Reg8::L),
+            0xae => self.res(bus, 5, operand::Indirect::HL),
+            0xaf => self.res(bus, 5, operand::Reg8::A),
+            0xb0 => self.res(bus, 6, operand::Reg8::B),
+            0xb1 => self.res(bus, 6, operand::Reg8::C),
+            0xb2 => self.res(bus, 6, operand::Reg8::D),
+            0xb3 => self.res(bus, 6, operand::Reg8::E),
+            0xb4 => self.res(bus, 6, operand::Reg8::H),
+            0xb5 => self.res(bus, 6, operand::Reg8::L),
+            0xb6 => self.res(bus, 6, operand::Indirect::HL),
+            0xb7 => self.res(bus, 6, operand::Reg8::A),
+            0xb8 => self.res(bus, 7, operand::Reg8::B),
+            0xb9 => self.res(bus, 7, operand::Reg8::C),
+            0xba => self.res(bus, 7, operand::Reg8::D),
+            0xbb => self.res(bus, 7, operand::Reg8::E),
+            0xbc => self.res(bus, 7, operand::Reg8::H),
+            0xbd => self.res(bus, 7, operand::Reg8::L),
+            0xbe => self.res(bus, 7, operand::Indirect::HL),
+            0xbf => self.res(bus, 7, operand::Reg8::A),
+            0xc0 => self.set(bus, 0, operand::Reg8::B),
+            0xc1 => self.set(bus, 0, operand::Reg8::C),
+            0xc2 => self.set(bus, 0, operand::Reg8::D),
+            0xc3 => self.set(bus, 0, operand::Reg8::E),
+            0xc4 => self.set(bus, 0, operand::Reg8::H),
+            0xc5 => self.set(bus, 0, operand::Reg8::L),
+            0xc6 => self.set(bus, 0, operand::Indirect::HL),
+            0xc7 => self.set(bus, 0, operand::Reg8::A),
+            0xc8 => self.set(bus, 1, operand::Reg8::B),
+            0xc9 => self.set(bus, 1, operand::Reg8::C),
+            0xca => self.set(bus, 1, operand::Reg8::D),
+            0xcb => self.set(bus, 1, operand::Reg8::E),
+            0xcc => self.set(bus, 1, operand::Reg8::H),
+            0xcd => self.set(bus, 1, operand::Reg8::L),
+            0xce => self.set(bus, 1, operand::Indirect::HL),
+            0xcf => self.set(bus, 1, operand::Reg8::A),
+            0xd0 => self.set(bus, 2, operand::Reg8::B),
+            0xd1 => self.set(bus, 2, operand::Reg8::C),
+            0xd2 => self.set(bus, 2, operand::Reg8::D),
+            0xd3 => self.set(bus, 2, operand::Reg8::E),
+            0xd4 => self.set(bus, 2, operand::Reg8::H),
+            0xd5 => self.set(bus, 2, operand::Reg8::L),
+            0xd6 => self.set(bus, 2, operand::Indirect::HL),
+            0xd7 => self.set(bus, 2, operand::Reg8::A),
+            0xd8 => self.set(bus, 3, operand::Reg8::B),
+            0xd9 => self.set(bus, 3, operand::Reg8::C),
+            0xda => self.set(bus, 3, operand::Reg8::D),
+            0xdb => self.set(bus, 3, operand::Reg8::E),
+            0xdc => self.set(bus, 3, operand::Reg8::H),
+            0xdd => self.set(bus, 3, operand::Reg8::L),
+            0xde => self.set(bus, 3, operand::Indirect::HL),
+            0xdf => self.set(bus, 3, operand::Reg8::A),
+            0xe0 => self.set(bus, 4, operand::Reg8::B),
+            0xe1 => self.set(bus, 4, operand::Reg8::C),
+            0xe2 => self.set(bus, 4, operand::Reg8::D),
+            0xe3 => self.set(bus, 4, operand::Reg8::E),
+            0xe4 => self.set(bus, 4, operand::Reg8::H),
+            0xe5 => self.set(bus, 4, operand::Reg8::L),
+            0xe6 => self.set(bus, 4, operand::Indirect::HL),
+            0xe7 => self.set(bus, 4, operand::Reg8::A),
+            0xe8 => self.set(bus, 5, operand::Reg8::B),
+            0xe9 => self.set(bus, 5, operand::Reg8::C),
+            0xea => self.set(bus, 5, operand::Reg8::D),
+            0xeb => self.set(bus, 5, operand::Reg8::E),
+            0xec => self.set(bus, 5, operand::Reg8::H),
+            0xed => self.set(bus, 5, operand::Reg8::L),
+            0xee => self.set(bus, 5, operand::Indirect::HL),
+            0xef => self.set(bus, 5, operand::Reg8::A),
+            0xf0 => self.set(bus, 6, operand::Reg8::B),
+            0xf1 => self.set(bus, 6, operand::Reg8::C),
+            0xf2 => self.set(bus, 6, operand::Reg8::D),
+            0xf3 => self.set(bus, 6, operand::Reg8::E),
+            0xf4 => self.set(bus, 6, operand::Reg8::H),
+            0xf5 => self.set(bus, 6, operand::Reg8::L),
+            0xf6 => self.set(bus, 6, operand::Indirect::HL),
+            0xf7 => self.set(bus, 6, operand::Reg8::A),
+            0xf8 => self.set(bus, 7, operand::Reg8::B),
+            0xf9 => self.set(bus, 7, operand::Reg8::C),
+            0xfa => self.set(bus, 7, operand::Reg8::D),
+            0xfb => self.set(bus, 7, operand::Reg8::E),
+            0xfc => self.set(bus, 7, operand::Reg8::H),
+            0xfd => self.set(bus, 7, operand::Reg8::L),
+            0xfe => self.set(bus, 7, operand::Indirect::HL),
+            0xff => self.set(bus, 7, operand::Reg8::A),
         }
     }
 }
@@ -84,11 +585,11 @@ mod tests {
         peripherals.write(0xc001, 0x34);
         peripherals.write(0xc002, 0x12);
         cpu.fetch(&peripherals);
-        for _ in 0..7 {
+        for _ in 0..3 {
             cpu.decode(&mut peripherals);
         }
         assert_eq!(cpu.regs.bc(), 0x1234);
-        assert_eq!(cpu.regs.pc, 0xc005);
+        assert_eq!(cpu.regs.pc, 0xc004);
     }
 
     #[test]
@@ -104,11 +605,11 @@ mod tests {
         peripherals.write(0xc001, 0x78);
         peripherals.write(0xc002, 0x56);
         cpu.fetch(&peripherals);
-        for _ in 0..7 {
+        for _ in 0..3 {
             cpu.decode(&mut peripherals);
         }
         assert_eq!(cpu.regs.de(), 0x5678);
-        assert_eq!(cpu.regs.pc, 0xc005);
+        assert_eq!(cpu.regs.pc, 0xc004);
     }
 
     #[test]
@@ -124,11 +625,11 @@ mod tests {
         peripherals.write(0xc001, 0xbc);
         peripherals.write(0xc002, 0x9a);
         cpu.fetch(&peripherals);
-        for _ in 0..7 {
+        for _ in 0..3 {
             cpu.decode(&mut peripherals);
         }
         assert_eq!(cpu.regs.hl(), 0x9abc);
-        assert_eq!(cpu.regs.pc, 0xc005);
+        assert_eq!(cpu.regs.pc, 0xc004);
     }
 
     #[test]
@@ -144,10 +645,618 @@ mod tests {
         peripherals.write(0xc001, 0xfe);
         peripherals.write(0xc002, 0xff);
         cpu.fetch(&peripherals);
-        for _ in 0..7 {
+        for _ in 0..3 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.sp, 0xfffe);
+        assert_eq!(cpu.regs.pc, 0xc004);
+    }
+
+    #[test]
+    fn test_decode_ld_r_r() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.c = 0x42;
+        peripherals.write(0xc000, 0x41);
+        cpu.fetch(&peripherals);
+        for _ in 0..1 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.b, 0x42);
+        assert_eq!(cpu.regs.pc, 0xc002);
+    }
+
+    #[test]
+    fn test_decode_ld_indirect_hl_imm() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.write_hl(0xc040);
+        peripherals.write(0xc000, 0x36);
+        peripherals.write(0xc001, 0x99);
+        cpu.fetch(&peripherals);
+        for _ in 0..3 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(peripherals.read(0xc040), 0x99);
+        assert_eq!(cpu.regs.pc, 0xc003);
+    }
+
+    #[test]
+    fn test_decode_ld_mcycles() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.write_de(0xc100);
+        cpu.regs.write_hl(0xc100);
+        // LD B,C; LD B,d8; LD (HL),B; LD B,(HL); LD (HL),d8; LD A,(HL+); LDH (a8),A; LDH A,(a8);
+        // LD (a16),A; LD A,(a16); LD (C),A; LD (DE),A
+        for (i, &byte) in [
+            0x41, 0x06, 0x42, 0x70, 0x46, 0x36, 0x99, 0x2a, 0xe0, 0x80, 0xf0, 0x80, 0xea, 0x00,
+            0xc1, 0xfa, 0x00, 0xc1, 0xe2, 0x12,
+        ]
+        .iter()
+        .enumerate()
+        {
+            peripherals.write(0xc000 + i as u16, byte);
+        }
+        cpu.fetch(&peripherals);
+        for (pc, expected) in [
+            (0xc002, 1),
+            (0xc004, 2),
+            (0xc005, 2),
+            (0xc006, 2),
+            (0xc008, 3),
+            (0xc009, 2),
+            (0xc00b, 3),
+            (0xc00d, 3),
+            (0xc010, 4),
+            (0xc013, 4),
+            (0xc014, 2),
+            (0xc015, 2),
+        ] {
+            let mut cycles = 0;
+            while cpu.regs.pc != pc {
+                cpu.decode(&mut peripherals);
+                cycles += 1;
+            }
+            assert_eq!(cycles, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_ld16_mcycles() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        cpu.regs.write_hl(0xc100);
+        // LD BC,d16; LD (a16),SP; LD SP,HL; LD HL,SP+e8
+        for (i, &byte) in [0x01, 0x34, 0x12, 0x08, 0x00, 0xc1, 0xf9, 0xf8, 0x01]
+            .iter()
+            .enumerate()
+        {
+            peripherals.write(0xc000 + i as u16, byte);
+        }
+        cpu.fetch(&peripherals);
+        for (pc, expected) in [(0xc004, 3), (0xc007, 5), (0xc008, 2), (0xc00a, 3)] {
+            let mut cycles = 0;
+            while cpu.regs.pc != pc {
+                cpu.decode(&mut peripherals);
+                cycles += 1;
+            }
+            assert_eq!(cycles, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_push_pop_mcycles() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        // PUSH BC; POP DE
+        for (i, &byte) in [0xc5, 0xd1].iter().enumerate() {
+            peripherals.write(0xc000 + i as u16, byte);
+        }
+        cpu.fetch(&peripherals);
+        for (pc, expected) in [(0xc002, 4), (0xc003, 3)] {
+            let mut cycles = 0;
+            while cpu.regs.pc != pc {
+                cpu.decode(&mut peripherals);
+                cycles += 1;
+            }
+            assert_eq!(cycles, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_jump_mcycles() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.write_hl(0xc040);
+        // JP a16
+        for (i, &byte) in [0xc3, 0x10, 0xc0].iter().enumerate() {
+            peripherals.write(0xc000 + i as u16, byte);
+        }
+        // JP NZ,a16 taken
+        for (i, &byte) in [0xc2, 0x20, 0xc0].iter().enumerate() {
+            peripherals.write(0xc010 + i as u16, byte);
+        }
+        // JP Z,a16 not taken; JR e8
+        for (i, &byte) in [0xca, 0x00, 0x00, 0x18, 0x05].iter().enumerate() {
+            peripherals.write(0xc020 + i as u16, byte);
+        }
+        // JR NZ,e8 taken
+        for (i, &byte) in [0x20, 0x05].iter().enumerate() {
+            peripherals.write(0xc02a + i as u16, byte);
+        }
+        // JR Z,e8 not taken; JP HL
+        for (i, &byte) in [0x28, 0x05, 0xe9].iter().enumerate() {
+            peripherals.write(0xc031 + i as u16, byte);
+        }
+        cpu.fetch(&peripherals);
+        for (pc, expected) in [
+            (0xc011, 4),
+            (0xc021, 4),
+            (0xc024, 3),
+            (0xc02b, 3),
+            (0xc032, 3),
+            (0xc034, 2),
+            (0xc041, 1),
+        ] {
+            let mut cycles = 0;
+            while cpu.regs.pc != pc {
+                cpu.decode(&mut peripherals);
+                cycles += 1;
+            }
+            assert_eq!(cycles, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_call_mcycles() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        // CALL a16; RST 28h
+        for (i, &byte) in [0xcd, 0x10, 0xc0, 0xef].iter().enumerate() {
+            peripherals.write(0xc000 + i as u16, byte);
+        }
+        // CALL NZ,a16 taken; RETI
+        for (i, &byte) in [0xc4, 0x20, 0xc0, 0xd9].iter().enumerate() {
+            peripherals.write(0xc010 + i as u16, byte);
+        }
+        // CALL Z,a16 not taken; RET Z not taken; RET NZ taken
+        for (i, &byte) in [0xcc, 0x00, 0x00, 0xc8, 0xc0].iter().enumerate() {
+            peripherals.write(0xc020 + i as u16, byte);
+        }
+        cpu.fetch(&peripherals);
+        for (pc, expected) in [
+            (0xc011, 6),
+            (0xc021, 6),
+            (0xc024, 3),
+            (0xc025, 2),
+            (0xc014, 5),
+            (0xc004, 4),
+            (0x0029, 4),
+        ] {
+            let mut cycles = 0;
+            while cpu.regs.pc != pc {
+                cpu.decode(&mut peripherals);
+                cycles += 1;
+            }
+            assert_eq!(cycles, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_inc_dec_mcycles() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.write_hl(0xc100);
+        // INC B; DEC B; INC (HL); DEC (HL); INC BC; DEC BC
+        for (i, &byte) in [0x04, 0x05, 0x34, 0x35, 0x03, 0x0b].iter().enumerate() {
+            peripherals.write(0xc000 + i as u16, byte);
+        }
+        cpu.fetch(&peripherals);
+        for (pc, expected) in [
+            (0xc002, 1),
+            (0xc003, 1),
+            (0xc004, 3),
+            (0xc005, 3),
+            (0xc006, 2),
+            (0xc007, 2),
+        ] {
+            let mut cycles = 0;
+            while cpu.regs.pc != pc {
+                cpu.decode(&mut peripherals);
+                cycles += 1;
+            }
+            assert_eq!(cycles, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_alu_mcycles() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        cpu.regs.write_hl(0xc100);
+        // ADD A,B; ADD A,(HL); ADD A,d8; ADD HL,BC; ADD SP,e8
+        for (i, &byte) in [0x80, 0x86, 0xc6, 0x01, 0x09, 0xe8, 0x01]
+            .iter()
+            .enumerate()
+        {
+            peripherals.write(0xc000 + i as u16, byte);
+        }
+        cpu.fetch(&peripherals);
+        for (pc, expected) in [
+            (0xc002, 1),
+            (0xc003, 2),
+            (0xc005, 2),
+            (0xc006, 2),
+            (0xc008, 4),
+        ] {
+            let mut cycles = 0;
+            while cpu.regs.pc != pc {
+                cpu.decode(&mut peripherals);
+                cycles += 1;
+            }
+            assert_eq!(cycles, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_ldh() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.a = 0x5a;
+        peripherals.write(0xc000, 0xe0);
+        peripherals.write(0xc001, 0x80);
+        peripherals.write(0xc002, 0xf0);
+        peripherals.write(0xc003, 0x80);
+        cpu.fetch(&peripherals);
+        while cpu.ctx.opcode == 0xe0 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(peripherals.read(0xff80), 0x5a);
+        cpu.regs.a = 0;
+        while cpu.ctx.opcode == 0xf0 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.a, 0x5a);
+    }
+
+    #[test]
+    fn test_decode_jp() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        peripherals.write(0xc000, 0xc3);
+        peripherals.write(0xc001, 0x10);
+        peripherals.write(0xc002, 0xc0);
+        cpu.fetch(&peripherals);
+        while cpu.ctx.opcode == 0xc3 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0xc011);
+    }
+
+    #[test]
+    fn test_decode_jp_c_not_taken() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.set_zf(true);
+        peripherals.write(0xc000, 0xc2);
+        peripherals.write(0xc001, 0x10);
+        peripherals.write(0xc002, 0xc0);
+        cpu.fetch(&peripherals);
+        while cpu.ctx.opcode == 0xc2 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0xc004);
+    }
+
+    #[test]
+    fn test_decode_call_ret() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        peripherals.write(0xc000, 0xcd);
+        peripherals.write(0xc001, 0x10);
+        peripherals.write(0xc002, 0xc0);
+        peripherals.write(0xc010, 0xc9);
+        cpu.fetch(&peripherals);
+        while cpu.ctx.opcode == 0xcd {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0xc011);
+        assert_eq!(cpu.regs.sp, 0xfffc);
+        while cpu.ctx.opcode == 0xc9 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0xc004);
+        assert_eq!(cpu.regs.sp, 0xfffe);
+    }
+
+    #[test]
+    fn test_decode_rst() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        peripherals.write(0xc000, 0xef);
+        cpu.fetch(&peripherals);
+        while cpu.ctx.opcode == 0xef {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.pc, 0x0029);
+        assert_eq!(peripherals.read(0xfffd), 0xc0);
+        assert_eq!(peripherals.read(0xfffc), 0x01);
+    }
+
+    #[test]
+    fn test_decode_push_pop() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.sp = 0xfffe;
+        cpu.regs.write_bc(0x1234);
+        peripherals.write(0xc000, 0xc5);
+        peripherals.write(0xc001, 0xd1);
+        cpu.fetch(&peripherals);
+        while cpu.ctx.opcode == 0xc5 {
             cpu.decode(&mut peripherals);
         }
+        while cpu.ctx.opcode == 0xd1 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.de(), 0x1234);
         assert_eq!(cpu.regs.sp, 0xfffe);
-        assert_eq!(cpu.regs.pc, 0xc005);
+    }
+
+    #[test]
+    fn test_decode_di_ei() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        peripherals.write(0xc000, 0xfb);
+        peripherals.write(0xc001, 0x00);
+        peripherals.write(0xc002, 0xf3);
+        cpu.fetch(&peripherals);
+        cpu.decode(&mut peripherals);
+        assert!(!cpu.ctx.ime);
+        cpu.decode(&mut peripherals);
+        assert!(cpu.ctx.ime);
+        cpu.decode(&mut peripherals);
+        assert!(!cpu.ctx.ime);
+    }
+
+    #[test]
+    fn test_decode_cb_rl() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.c = 0x80;
+        peripherals.write(0xc000, 0xcb);
+        peripherals.write(0xc001, 0x11);
+        cpu.fetch(&peripherals);
+        cpu.decode(&mut peripherals);
+        assert_eq!(cpu.regs.c, 0x80);
+        cpu.decode(&mut peripherals);
+        assert!(!cpu.ctx.cb);
+        assert_eq!(cpu.regs.c, 0x00);
+        assert!(cpu.regs.zf());
+        assert!(cpu.regs.cf());
+        assert_eq!(cpu.regs.pc, 0xc003);
+    }
+
+    #[test]
+    fn test_decode_cb_bit() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.h = 0x80;
+        peripherals.write(0xc000, 0xcb);
+        peripherals.write(0xc001, 0x7c);
+        cpu.fetch(&peripherals);
+        for _ in 0..2 {
+            cpu.decode(&mut peripherals);
+        }
+        assert!(!cpu.regs.zf());
+        assert!(cpu.regs.hf());
+        assert_eq!(cpu.regs.pc, 0xc003);
+    }
+
+    #[test]
+    fn test_decode_cb_mcycles() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.write_hl(0xc100);
+        // RL C; BIT 7,H; SET 0,A; RL (HL); BIT 0,(HL); SET 0,(HL)
+        for (i, &byte) in [
+            0xcb, 0x11, 0xcb, 0x7c, 0xcb, 0xc7, 0xcb, 0x16, 0xcb, 0x46, 0xcb, 0xc6,
+        ]
+        .iter()
+        .enumerate()
+        {
+            peripherals.write(0xc000 + i as u16, byte);
+        }
+        cpu.fetch(&peripherals);
+        for (pc, expected) in [
+            (0xc003, 2),
+            (0xc005, 2),
+            (0xc007, 2),
+            (0xc009, 4),
+            (0xc00b, 3),
+            (0xc00d, 4),
+        ] {
+            let mut cycles = 0;
+            while cpu.regs.pc != pc {
+                cpu.decode(&mut peripherals);
+                cycles += 1;
+            }
+            assert_eq!(cycles, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_cb_res_indirect() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.write_hl(0xc040);
+        peripherals.write(0xc040, 0xff);
+        peripherals.write(0xc000, 0xcb);
+        peripherals.write(0xc001, 0x86);
+        cpu.fetch(&peripherals);
+        let mut cycles = 0;
+        while cpu.regs.pc != 0xc003 {
+            cpu.decode(&mut peripherals);
+            cycles += 1;
+        }
+        assert_eq!(peripherals.read(0xc040), 0xfe);
+        assert_eq!(cycles, 4);
+    }
+
+    #[test]
+    fn test_decode_alu() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.a = 0x12;
+        peripherals.write(0xc000, 0xaf);
+        peripherals.write(0xc001, 0xc6);
+        peripherals.write(0xc002, 0x0f);
+        peripherals.write(0xc003, 0xd6);
+        peripherals.write(0xc004, 0x10);
+        cpu.fetch(&peripherals);
+        cpu.decode(&mut peripherals);
+        assert_eq!(cpu.regs.a, 0x00);
+        assert!(cpu.regs.zf());
+        for _ in 0..2 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.a, 0x0f);
+        for _ in 0..2 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.a, 0xff);
+        assert!(cpu.regs.cf());
+        assert_eq!(cpu.regs.pc, 0xc006);
+    }
+
+    #[test]
+    fn test_decode_add_hl() {
+        let mut cpu = cpu::Cpu {
+            regs: crate::registers::Registers::default(),
+            ctx: cpu::Ctx::default(),
+        };
+        let bootrom = crate::bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = peripherals::Peripherals::new(bootrom);
+        cpu.regs.pc = 0xc000;
+        cpu.regs.write_hl(0x1000);
+        cpu.regs.write_de(0x0234);
+        peripherals.write(0xc000, 0x19);
+        cpu.fetch(&peripherals);
+        for _ in 0..2 {
+            cpu.decode(&mut peripherals);
+        }
+        assert_eq!(cpu.regs.hl(), 0x1234);
+        assert_eq!(cpu.regs.pc, 0xc002);
     }
 }
@@ -0,0 +1,135 @@
+const OAM_SIZE: u16 = 0xa0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bus {
+    External,
+    Video,
+    Internal,
+}
+
+fn bus(addr: u16) -> Bus {
+    match addr {
+        0x8000..=0x9fff => Bus::Video,
+        0xfe00..=0xffff => Bus::Internal,
+        _ => Bus::External,
+    }
+}
+
+// OAM DMA copies one byte per M-cycle from XX00-XX9F after a one cycle setup. Sources from
+// 0xe000 up read the echo of work RAM.
+#[derive(Default)]
+pub struct Dma {
+    reg: u8,
+    addr: u16,
+    active: bool,
+    restart: bool,
+    last: u8,
+}
+impl Dma {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn read(&self) -> u8 {
+        self.reg
+    }
+    // A restart only takes over after its setup cycle; the running transfer keeps going until
+    // then.
+    pub fn write(&mut self, val: u8) {
+        self.reg = val;
+        self.restart = true;
+    }
+    // Returns the source address to copy this cycle. The low byte is the OAM offset.
+    pub fn tick(&mut self) -> Option<u16> {
+        let transfer = self.active.then(|| {
+            let addr = self.addr;
+            self.addr += 1;
+            self.active = self.addr & 0xff < OAM_SIZE;
+            addr
+        });
+        if self.restart {
+            self.restart = false;
+            self.active = true;
+            self.addr = (self.reg as u16) << 8;
+            if self.addr >= 0xe000 {
+                self.addr -= 0x2000;
+            }
+        }
+        transfer
+    }
+    pub fn set_last(&mut self, val: u8) {
+        self.last = val;
+    }
+    // What the CPU sees instead of addr while a transfer runs, if anything. OAM is busy and
+    // the bus the transfer reads from returns the byte on it; HRAM and I/O stay reachable.
+    pub fn conflict(&self, addr: u16) -> Option<u8> {
+        if !self.active {
+            return None;
+        }
+        match addr {
+            0xfe00..=0xfe9f => Some(0xff),
+            _ if bus(addr) != Bus::Internal && bus(addr) == bus(self.addr) => Some(self.last),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer() {
+        let mut dma = Dma::new();
+        assert_eq!(dma.tick(), None);
+        dma.write(0xc1);
+        assert_eq!(dma.read(), 0xc1);
+        assert_eq!(dma.tick(), None);
+        for i in 0..0xa0 {
+            assert_eq!(dma.tick(), Some(0xc100 + i));
+        }
+        assert_eq!(dma.tick(), None);
+        assert_eq!(dma.conflict(0xc000), None);
+    }
+
+    #[test]
+    fn test_restart() {
+        let mut dma = Dma::new();
+        dma.write(0xc0);
+        dma.tick();
+        for _ in 0..0x10 {
+            dma.tick();
+        }
+        dma.write(0xd0);
+        assert_eq!(dma.tick(), Some(0xc010));
+        assert_eq!(dma.tick(), Some(0xd000));
+        for _ in 1..0xa0 {
+            assert!(dma.tick().is_some());
+        }
+        assert_eq!(dma.tick(), None);
+    }
+
+    #[test]
+    fn test_echo_source() {
+        let mut dma = Dma::new();
+        dma.write(0xfe);
+        dma.tick();
+        assert_eq!(dma.tick(), Some(0xde00));
+    }
+
+    #[test]
+    fn test_conflict() {
+        let mut dma = Dma::new();
+        dma.write(0x80);
+        dma.tick();
+        dma.set_last(0x42);
+        assert_eq!(dma.conflict(0xfe00), Some(0xff));
+        assert_eq!(dma.conflict(0x9000), Some(0x42));
+        assert_eq!(dma.conflict(0xc000), None);
+        assert_eq!(dma.conflict(0xff80), None);
+        dma.write(0xc0);
+        dma.tick();
+        assert_eq!(dma.conflict(0x9000), None);
+        assert_eq!(dma.conflict(0x4000), Some(0x42));
+        assert_eq!(dma.conflict(0xa000), Some(0x42));
+    }
+}
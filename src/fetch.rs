@@ -3,9 +3,18 @@ use crate::peripherals;
 
 impl cpu::Cpu {
     pub fn fetch(&mut self, bus: &peripherals::Peripherals) {
+        if self.ctx.ime_delay {
+            self.ctx.ime = true;
+            self.ctx.ime_delay = false;
+        }
         self.ctx.opcode = bus.read(self.regs.pc);
-        self.regs.pc = self.regs.pc.wrapping_add(1);
+        self.ctx.int = self.ctx.ime && bus.interrupts.irq() > 0;
+        if !self.ctx.int && !self.ctx.halt_bug {
+            self.regs.pc = self.regs.pc.wrapping_add(1);
+        }
+        self.ctx.halt_bug = false;
         self.ctx.cb = false;
+        self.ctx.fetched = true;
     }
 }
 
@@ -29,6 +38,6 @@ mod tests {
 
         assert_eq!(cpu.ctx.opcode, 42);
         assert_eq!(cpu.regs.pc, 0xc001);
-        assert_eq!(cpu.ctx.cb, false);
+        assert!(!cpu.ctx.cb);
     }
 }
@@ -0,0 +1,332 @@
+use crate::bootrom;
+use crate::cartridge;
+use crate::cpu;
+use crate::joypad;
+use crate::peripherals;
+use crate::ppu;
+use crate::registers;
+
+pub const MCYCLES_PER_FRAME: u32 = 17556;
+
+pub struct GameBoy {
+    cpu: cpu::Cpu,
+    peripherals: peripherals::Peripherals,
+    mcycles: u64,
+}
+impl GameBoy {
+    pub fn new(bootrom: bootrom::Bootrom) -> Self {
+        Self {
+            cpu: cpu::Cpu::default(),
+            peripherals: peripherals::Peripherals::new(bootrom),
+            mcycles: 0,
+        }
+    }
+    pub fn insert_cartridge(&mut self, cartridge: cartridge::Cartridge) {
+        self.peripherals.insert_cartridge(cartridge);
+    }
+    pub fn cartridge(&self) -> Option<&cartridge::Cartridge> {
+        self.peripherals.cartridge()
+    }
+    pub fn cartridge_mut(&mut self) -> Option<&mut cartridge::Cartridge> {
+        self.peripherals.cartridge_mut()
+    }
+    pub fn has_battery(&self) -> bool {
+        self.cartridge()
+            .is_some_and(|cartridge| cartridge.header().has_battery())
+    }
+    pub fn export_save(&self) -> Option<Vec<u8>> {
+        self.cartridge().map(|cartridge| cartridge.save_data())
+    }
+    pub fn export_save_at(&self, now: u64) -> Option<Vec<u8>> {
+        self.cartridge()
+            .map(|cartridge| cartridge.save_data_at(now))
+    }
+    pub fn import_save(&mut self, data: &[u8]) {
+        if let Some(cartridge) = self.cartridge_mut() {
+            cartridge.load_save_data(data);
+        }
+    }
+    pub fn rumble(&self) -> bool {
+        self.cartridge().is_some_and(|cartridge| cartridge.rumble())
+    }
+    pub fn mcycles(&self) -> u64 {
+        self.mcycles
+    }
+    pub fn press(&mut self, button: joypad::Button) {
+        self.peripherals.press(button);
+    }
+    pub fn release(&mut self, button: joypad::Button) {
+        self.peripherals.release(button);
+    }
+    // Sets the rate in Hz that drain_samples produces stereo pairs at.
+    pub fn set_sample_rate(&mut self, rate: u32) {
+        self.peripherals.set_sample_rate(rate);
+    }
+    // Keeps a frame-locked host's audio buffer near `target` stereo frames given how many are
+    // `queued`, by running the output rate up to 0.5% fast or slow.
+    pub fn adjust_sample_rate(&mut self, queued: usize, target: usize) {
+        self.peripherals.adjust_sample_rate(queued, target);
+    }
+    pub fn drain_samples(&mut self) -> Vec<f32> {
+        self.peripherals.drain_samples()
+    }
+    pub fn set_renderer(&mut self, renderer: ppu::Renderer) {
+        self.peripherals.set_renderer(renderer);
+    }
+    pub fn frame(&self) -> &[u16] {
+        self.peripherals.frame()
+    }
+    pub fn registers(&self) -> &registers::Registers {
+        &self.cpu.regs
+    }
+    pub fn read(&self, addr: u16) -> u8 {
+        self.peripherals.read(addr)
+    }
+    pub fn write(&mut self, addr: u16, val: u8) {
+        self.peripherals.write(addr, val);
+    }
+    pub fn step_mcycle(&mut self) {
+        if !self.peripherals.cpu_stalled() {
+            self.cpu.emulate_cycle(&mut self.peripherals);
+        } else {
+            self.cpu.ctx.fetched = false;
+        }
+        self.peripherals.tick();
+        self.mcycles += 1;
+    }
+    pub fn step_instruction(&mut self) {
+        loop {
+            self.step_mcycle();
+            let ctx = &self.cpu.ctx;
+            if ctx.fetched || ctx.halt || ctx.stop || ctx.locked {
+                break;
+            }
+        }
+    }
+    // Runs until the PPU enters VBlank, or for one frame's worth of cycles while the LCD is off.
+    // A frame takes twice as many M-cycles in double speed.
+    pub fn run_frame(&mut self) {
+        let frames = self.peripherals.frames();
+        let speed = if self.peripherals.double_speed() {
+            2
+        } else {
+            1
+        };
+        for _ in 0..MCYCLES_PER_FRAME * speed {
+            self.step_mcycle();
+            if self.peripherals.frames() != frames {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_step_mcycle() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        gameboy.step_mcycle();
+        assert_eq!(gameboy.cpu.regs.pc, 1);
+        gameboy.step_mcycle();
+        assert_eq!(gameboy.cpu.regs.pc, 2);
+    }
+
+    #[test]
+    fn test_step_instruction() {
+        let mut bootrom_data = vec![0; 256];
+        bootrom_data[0x00] = 0x31;
+        bootrom_data[0x01] = 0xfe;
+        bootrom_data[0x02] = 0xff;
+        bootrom_data[0x03] = 0xaf;
+        bootrom_data[0x04] = 0x21;
+        bootrom_data[0x05] = 0xff;
+        bootrom_data[0x06] = 0x9f;
+        bootrom_data[0x07] = 0xcb;
+        bootrom_data[0x08] = 0x7c;
+        let bootrom = bootrom::Bootrom::new(bootrom_data.into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        gameboy.step_instruction();
+        assert_eq!(gameboy.cpu.ctx.opcode, 0x31);
+        gameboy.step_instruction();
+        assert_eq!(gameboy.cpu.regs.sp, 0xfffe);
+        assert_eq!(gameboy.cpu.ctx.opcode, 0xaf);
+        gameboy.step_instruction();
+        assert!(gameboy.cpu.regs.zf());
+        gameboy.step_instruction();
+        assert_eq!(gameboy.cpu.regs.hl(), 0x9fff);
+        gameboy.step_instruction();
+        assert!(!gameboy.cpu.regs.zf());
+        assert_eq!(gameboy.cpu.regs.pc, 0x0a);
+    }
+
+    #[test]
+    fn test_step_instruction_mcycles() {
+        let mut bootrom_data = vec![0; 256];
+        // LD SP,d16; LD A,d8; LD B,C; JP a16
+        bootrom_data[0x00..0x09]
+            .copy_from_slice(&[0x31, 0xfe, 0xff, 0x3e, 0x42, 0x41, 0xc3, 0x10, 0x00]);
+        // CALL a16; JR -2
+        bootrom_data[0x10..0x15].copy_from_slice(&[0xcd, 0x20, 0x00, 0x18, 0xfe]);
+        // RET
+        bootrom_data[0x20] = 0xc9;
+        let bootrom = bootrom::Bootrom::new(bootrom_data.into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        gameboy.step_instruction();
+        let start = gameboy.mcycles();
+        for _ in 0..6 {
+            gameboy.step_instruction();
+        }
+        assert_eq!(gameboy.mcycles() - start, 3 + 2 + 1 + 4 + 6 + 4);
+        assert_eq!(gameboy.cpu.ctx.opcode, 0x18);
+        assert_eq!(gameboy.registers().pc, 0x14);
+        assert_eq!(gameboy.registers().sp, 0xfffe);
+    }
+
+    #[test]
+    fn test_step_instruction_halted() {
+        let mut bootrom_data = vec![0; 256];
+        bootrom_data[0x00] = 0x76;
+        let bootrom = bootrom::Bootrom::new(bootrom_data.into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        gameboy.step_instruction();
+        gameboy.step_instruction();
+        assert!(gameboy.cpu.ctx.halt);
+        gameboy.step_instruction();
+        assert!(gameboy.cpu.ctx.halt);
+        assert_eq!(gameboy.cpu.regs.pc, 0x01);
+    }
+
+    #[test]
+    fn test_illegal_opcode() {
+        let mut bootrom_data = vec![0; 256];
+        bootrom_data[0x00] = 0xd3;
+        let bootrom = bootrom::Bootrom::new(bootrom_data.into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        gameboy.step_instruction();
+        gameboy.step_instruction();
+        assert!(gameboy.cpu.ctx.locked);
+        assert_eq!(gameboy.registers().pc, 0x01);
+        gameboy.cpu.ctx.ime = true;
+        gameboy.write(0xffff, crate::interrupts::VBLANK);
+        gameboy.write(0xff0f, crate::interrupts::VBLANK);
+        let sp = gameboy.registers().sp;
+        let start = gameboy.mcycles();
+        for _ in 0..100 {
+            gameboy.step_mcycle();
+        }
+        gameboy.step_instruction();
+        assert_eq!(gameboy.mcycles() - start, 101);
+        assert_eq!(gameboy.registers().pc, 0x01);
+        assert_eq!(gameboy.registers().sp, sp);
+    }
+
+    #[test]
+    fn test_read_write() {
+        let bootrom = bootrom::Bootrom::new(vec![0x5a; 256].into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        assert_eq!(gameboy.read(0x0000), 0x5a);
+        gameboy.write(0xc000, 0x42);
+        assert_eq!(gameboy.read(0xc000), 0x42);
+        gameboy.step_mcycle();
+        assert_eq!(gameboy.registers().pc, 1);
+    }
+
+    #[test]
+    fn test_run_frame_vblank() {
+        let mut bootrom_data = vec![0; 256];
+        bootrom_data[0x00..0x06].copy_from_slice(&[0x3e, 0x91, 0xe0, 0x40, 0x18, 0xfe]);
+        let bootrom = bootrom::Bootrom::new(bootrom_data.into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        gameboy.run_frame();
+        assert_eq!(gameboy.read(0xff44), 144);
+        let mcycles = gameboy.mcycles();
+        gameboy.run_frame();
+        assert_eq!(gameboy.mcycles() - mcycles, MCYCLES_PER_FRAME as u64);
+        assert_eq!(gameboy.frame().len(), 160 * 144);
+    }
+
+    #[test]
+    fn test_save() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        assert!(!gameboy.has_battery());
+        assert_eq!(gameboy.export_save(), None);
+        let rom = cartridge::tests::build_rom(0x03, 0x00, 0x01);
+        gameboy.insert_cartridge(cartridge::Cartridge::new(rom.into_boxed_slice()).unwrap());
+        assert!(gameboy.has_battery());
+        let mut data = vec![0; 0x800];
+        data[0x10] = 0x42;
+        gameboy.import_save(&data);
+        gameboy.write(0x0000, 0x0a);
+        assert_eq!(gameboy.read(0xa010), 0x42);
+        gameboy.write(0xa011, 0x43);
+        let data = gameboy.export_save().unwrap();
+        assert_eq!(data.len(), 0x800);
+        assert_eq!(data[0x11], 0x43);
+    }
+
+    #[test]
+    fn test_double_speed() {
+        let mut bootrom_data = vec![0; 256];
+        bootrom_data[0x00..0x0c].copy_from_slice(&[
+            0x3e, 0x01, 0xe0, 0x4d, 0x10, 0x00, 0x3e, 0x91, 0xe0, 0x40, 0x18, 0xfe,
+        ]);
+        let bootrom = bootrom::Bootrom::new(bootrom_data.into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        let mut rom = cartridge::tests::build_rom(0x00, 0x00, 0x00);
+        rom[0x143] = 0xc0;
+        cartridge::tests::fix_checksums(&mut rom);
+        gameboy.insert_cartridge(cartridge::Cartridge::new(rom.into_boxed_slice()).unwrap());
+        for _ in 0..4 {
+            gameboy.step_instruction();
+        }
+        assert_eq!(gameboy.read(0xff4d), 0xfe);
+        assert_eq!(gameboy.registers().pc, 0x07);
+        gameboy.run_frame();
+        let mcycles = gameboy.mcycles();
+        gameboy.drain_samples();
+        gameboy.run_frame();
+        assert_eq!(gameboy.mcycles() - mcycles, 2 * MCYCLES_PER_FRAME as u64);
+        // The APU keeps its own rate, so a frame still makes 1/59.7 s of audio.
+        assert!((803..=804).contains(&(gameboy.drain_samples().len() / 2)));
+    }
+
+    #[test]
+    fn test_stop_press() {
+        let mut bootrom_data = vec![0; 256];
+        bootrom_data[0x00..0x08].copy_from_slice(&[0x3e, 0x10, 0xe0, 0x00, 0x10, 0x00, 0x18, 0xfe]);
+        let bootrom = bootrom::Bootrom::new(bootrom_data.into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        for _ in 0..4 {
+            gameboy.step_instruction();
+        }
+        assert!(gameboy.cpu.ctx.stop);
+        for _ in 0..100 {
+            gameboy.step_mcycle();
+        }
+        assert_eq!(gameboy.registers().pc, 0x06);
+        gameboy.press(joypad::Button::Right);
+        gameboy.step_mcycle();
+        assert!(gameboy.cpu.ctx.stop);
+        gameboy.press(joypad::Button::Start);
+        gameboy.step_instruction();
+        assert!(!gameboy.cpu.ctx.stop);
+        assert_eq!(gameboy.registers().pc, 0x07);
+        assert_eq!(gameboy.read(0xff00), 0xd7);
+    }
+
+    #[test]
+    fn test_run_frame() {
+        let mut bootrom_data = vec![0; 256];
+        bootrom_data[0x00] = 0x18;
+        bootrom_data[0x01] = 0xfe;
+        let bootrom = bootrom::Bootrom::new(bootrom_data.into_boxed_slice());
+        let mut gameboy = GameBoy::new(bootrom);
+        gameboy.run_frame();
+        assert_eq!(gameboy.mcycles(), MCYCLES_PER_FRAME as u64);
+        assert!(gameboy.cpu.regs.pc <= 0x02);
+    }
+}
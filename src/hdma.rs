@@ -0,0 +1,144 @@
+const BLOCK: u16 = 0x10;
+
+// CGB VRAM DMA. A general purpose transfer copies every block in one go; an HBlank transfer
+// copies one 16 byte block at the start of each HBlank. The CPU is stopped while bytes move.
+#[derive(Default)]
+pub struct Hdma {
+    source: u16,
+    dest: u16,
+    // Blocks left minus one, as HDMA5 reports it.
+    len: u8,
+    active: bool,
+    hblank: bool,
+    pending: u16,
+}
+impl Hdma {
+    pub fn new() -> Self {
+        Self {
+            len: 0x7f,
+            ..Self::default()
+        }
+    }
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xff55 if self.active => self.len,
+            0xff55 => 0x80 | self.len,
+            _ => 0xff,
+        }
+    }
+    // Returns true when an HBlank transfer is started, so the caller can run the first block
+    // straight away if the LCD is off.
+    pub fn write(&mut self, addr: u16, val: u8) -> bool {
+        match addr {
+            0xff51 => self.source = (self.source & 0x00ff) | (val as u16) << 8,
+            0xff52 => self.source = (self.source & 0xff00) | (val & 0xf0) as u16,
+            0xff53 => self.dest = (self.dest & 0x00ff) | ((val & 0x1f) as u16) << 8,
+            0xff54 => self.dest = (self.dest & 0xff00) | (val & 0xf0) as u16,
+            0xff55 => {
+                // Clearing bit 7 during an HBlank transfer cancels it instead of starting a
+                // general purpose one.
+                if self.active && self.hblank && val & 0x80 == 0 {
+                    self.active = false;
+                    return false;
+                }
+                self.len = val & 0x7f;
+                self.active = true;
+                self.hblank = val & 0x80 > 0;
+                if !self.hblank {
+                    self.pending = (self.len as u16 + 1) * BLOCK;
+                }
+                return self.hblank;
+            }
+            _ => unreachable!(),
+        }
+        false
+    }
+    pub fn hblank(&mut self) {
+        if self.active && self.hblank && self.pending == 0 {
+            self.pending = BLOCK;
+        }
+    }
+    pub fn is_copying(&self) -> bool {
+        self.pending > 0
+    }
+    // Returns the next source and VRAM offset to copy. Sources from 0xe000 up read 0xa000-0xbfff.
+    pub fn next(&mut self) -> Option<(u16, u16)> {
+        if self.pending == 0 {
+            return None;
+        }
+        let source = if self.source >= 0xe000 {
+            self.source - 0x4000
+        } else {
+            self.source
+        };
+        let dest = self.dest;
+        self.source = self.source.wrapping_add(1);
+        self.dest = (self.dest + 1) & 0x1fff;
+        self.pending -= 1;
+        if self.source.is_multiple_of(BLOCK) {
+            if self.len == 0 {
+                self.active = false;
+            }
+            self.len = self.len.wrapping_sub(1) & 0x7f;
+        }
+        Some((source, dest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(hdma: &mut Hdma, source: u16, dest: u16, hdma5: u8) -> bool {
+        hdma.write(0xff51, (source >> 8) as u8);
+        hdma.write(0xff52, source as u8);
+        hdma.write(0xff53, (dest >> 8) as u8);
+        hdma.write(0xff54, dest as u8);
+        hdma.write(0xff55, hdma5)
+    }
+
+    #[test]
+    fn test_general_purpose() {
+        let mut hdma = Hdma::new();
+        assert_eq!(hdma.read(0xff55), 0xff);
+        assert!(!start(&mut hdma, 0xc12f, 0x9ff5, 0x01));
+        assert_eq!(hdma.read(0xff55), 0x01);
+        assert!(hdma.is_copying());
+        assert_eq!(hdma.next(), Some((0xc120, 0x1ff0)));
+        for i in 1..0x20 {
+            assert_eq!(hdma.next(), Some((0xc120 + i, (0x1ff0 + i) & 0x1fff)));
+            if i == 0x0f {
+                assert_eq!(hdma.read(0xff55), 0x00);
+            }
+        }
+        assert_eq!(hdma.next(), None);
+        assert!(!hdma.is_copying());
+        assert_eq!(hdma.read(0xff55), 0xff);
+    }
+
+    #[test]
+    fn test_hblank() {
+        let mut hdma = Hdma::new();
+        assert!(start(&mut hdma, 0xe000, 0x8000, 0x82));
+        assert!(!hdma.is_copying());
+        assert_eq!(hdma.read(0xff55), 0x02);
+        hdma.hblank();
+        for i in 0..0x10 {
+            assert_eq!(hdma.next(), Some((0xa000 + i, i)));
+        }
+        assert_eq!(hdma.next(), None);
+        assert_eq!(hdma.read(0xff55), 0x01);
+        hdma.hblank();
+        for _ in 0..0x10 {
+            hdma.next();
+        }
+        hdma.write(0xff55, 0x00);
+        assert_eq!(hdma.read(0xff55), 0x80);
+        hdma.hblank();
+        assert!(!hdma.is_copying());
+
+        hdma.write(0xff55, 0x80);
+        hdma.hblank();
+        assert_eq!(hdma.next(), Some((0xa020, 0x0020)));
+    }
+}
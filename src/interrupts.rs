@@ -0,0 +1,70 @@
+pub const VBLANK: u8 = 1 << 0;
+pub const STAT: u8 = 1 << 1;
+pub const TIMER: u8 = 1 << 2;
+// No serial port yet, so only tests raise it.
+#[allow(dead_code)]
+pub const SERIAL: u8 = 1 << 3;
+pub const JOYPAD: u8 = 1 << 4;
+
+#[derive(Clone, Default)]
+pub struct Interrupts {
+    pub int_flags: u8,
+    pub int_enable: u8,
+}
+impl Interrupts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn request(&mut self, val: u8) {
+        self.int_flags |= val;
+    }
+    pub fn irq(&self) -> u8 {
+        self.int_flags & self.int_enable & 0x1f
+    }
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xff0f => self.int_flags | 0xe0,
+            0xffff => self.int_enable,
+            _ => unreachable!(),
+        }
+    }
+    pub fn write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0xff0f => self.int_flags = val & 0x1f,
+            0xffff => self.int_enable = val,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_request() {
+        let mut interrupts = Interrupts::new();
+        interrupts.request(VBLANK);
+        interrupts.request(TIMER);
+        assert_eq!(interrupts.int_flags, VBLANK | TIMER);
+    }
+
+    #[test]
+    fn test_irq_masked_by_enable() {
+        let mut interrupts = Interrupts::new();
+        interrupts.request(VBLANK | JOYPAD);
+        assert_eq!(interrupts.irq(), 0);
+        interrupts.write(0xffff, JOYPAD);
+        assert_eq!(interrupts.irq(), JOYPAD);
+    }
+
+    #[test]
+    fn test_read_write() {
+        let mut interrupts = Interrupts::new();
+        interrupts.write(0xff0f, 0xff);
+        interrupts.write(0xffff, 0xff);
+        assert_eq!(interrupts.read(0xff0f), 0xff);
+        assert_eq!(interrupts.int_flags, 0x1f);
+        assert_eq!(interrupts.read(0xffff), 0xff);
+        assert_eq!(interrupts.irq(), 0x1f);
+    }
+}
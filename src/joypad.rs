@@ -0,0 +1,121 @@
+use crate::interrupts;
+
+const SELECT_DIRECTIONS: u8 = 1 << 4;
+const SELECT_BUTTONS: u8 = 1 << 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+impl Button {
+    // Directions take the low nibble and buttons the high one, each in P1 bit order.
+    fn mask(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+// P1 reads the pressed keys of whichever groups bits 4 and 5 select, active low. The interrupt
+// fires whenever one of the four input lines falls, whether from a press or a select change.
+pub struct Joypad {
+    select: u8,
+    pressed: u8,
+}
+impl Joypad {
+    pub fn new() -> Self {
+        Self {
+            select: SELECT_DIRECTIONS | SELECT_BUTTONS,
+            pressed: 0,
+        }
+    }
+    // Low bits are pressed keys, as the inverse of what P1 shows.
+    fn lines(&self) -> u8 {
+        let mut lines = 0;
+        if self.select & SELECT_DIRECTIONS == 0 {
+            lines |= self.pressed & 0x0f;
+        }
+        if self.select & SELECT_BUTTONS == 0 {
+            lines |= self.pressed >> 4;
+        }
+        lines
+    }
+    fn update(&mut self, interrupts: &mut interrupts::Interrupts, f: impl FnOnce(&mut Self)) {
+        let old = self.lines();
+        f(self);
+        if self.lines() & !old > 0 {
+            interrupts.request(interrupts::JOYPAD);
+        }
+    }
+    pub fn press(&mut self, interrupts: &mut interrupts::Interrupts, button: Button) {
+        self.update(interrupts, |joypad| joypad.pressed |= button.mask());
+    }
+    pub fn release(&mut self, interrupts: &mut interrupts::Interrupts, button: Button) {
+        self.update(interrupts, |joypad| joypad.pressed &= !button.mask());
+    }
+    pub fn read(&self) -> u8 {
+        0xc0 | self.select | (!self.lines() & 0x0f)
+    }
+    pub fn write(&mut self, interrupts: &mut interrupts::Interrupts, val: u8) {
+        self.update(interrupts, |joypad| {
+            joypad.select = val & (SELECT_DIRECTIONS | SELECT_BUTTONS)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let mut joypad = Joypad::new();
+        let mut interrupts = interrupts::Interrupts::new();
+        joypad.press(&mut interrupts, Button::Down);
+        joypad.press(&mut interrupts, Button::A);
+        assert_eq!(joypad.read(), 0xff);
+        joypad.write(&mut interrupts, 0x20);
+        assert_eq!(joypad.read(), 0xe7);
+        joypad.write(&mut interrupts, 0x10);
+        assert_eq!(joypad.read(), 0xde);
+        joypad.write(&mut interrupts, 0x00);
+        assert_eq!(joypad.read(), 0xc6);
+        joypad.release(&mut interrupts, Button::Down);
+        joypad.press(&mut interrupts, Button::Start);
+        assert_eq!(joypad.read(), 0xc6);
+        joypad.release(&mut interrupts, Button::A);
+        assert_eq!(joypad.read(), 0xc7);
+        joypad.write(&mut interrupts, 0xff);
+        assert_eq!(joypad.read(), 0xff);
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let mut joypad = Joypad::new();
+        let mut interrupts = interrupts::Interrupts::new();
+        joypad.press(&mut interrupts, Button::B);
+        assert_eq!(interrupts.int_flags, 0);
+        joypad.write(&mut interrupts, 0x20);
+        assert_eq!(interrupts.int_flags, 0);
+        joypad.write(&mut interrupts, 0x10);
+        assert_eq!(interrupts.int_flags, interrupts::JOYPAD);
+
+        interrupts.int_flags = 0;
+        joypad.write(&mut interrupts, 0x20);
+        joypad.press(&mut interrupts, Button::Start);
+        assert_eq!(interrupts.int_flags, 0);
+        joypad.press(&mut interrupts, Button::Up);
+        assert_eq!(interrupts.int_flags, interrupts::JOYPAD);
+
+        // Releases and deselecting only raise lines.
+        interrupts.int_flags = 0;
+        joypad.release(&mut interrupts, Button::Up);
+        joypad.write(&mut interrupts, 0x30);
+        assert_eq!(interrupts.int_flags, 0);
+    }
+}
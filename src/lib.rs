@@ -1,10 +1,30 @@
+mod apu;
 mod bootrom;
+mod cartridge;
 mod cpu;
 mod decode;
+mod dma;
 mod fetch;
+mod gameboy;
+mod hdma;
 mod hram;
 mod instructions;
+mod interrupts;
+mod joypad;
 mod operand;
 mod peripherals;
+mod ppu;
 mod registers;
+mod save;
+mod timer;
 mod wram;
+
+pub use bootrom::Bootrom;
+pub use cartridge::{
+    CAMERA_HEIGHT, CAMERA_WIDTH, Cartridge, Error as CartridgeError, Header, ImageSource, Licensee,
+};
+pub use gameboy::{GameBoy, MCYCLES_PER_FRAME};
+pub use joypad::Button;
+pub use ppu::{Renderer, SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use registers::Registers;
+pub use save::SaveFile;
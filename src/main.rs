@@ -1,8 +1,191 @@
-mod bootrom;
-mod hram;
-mod peripherals;
-mod wram;
+use std::{env, fs, path, process, thread, time};
+
+const FLUSH_INTERVAL_FRAMES: u32 = 300;
+const SCALE: u32 = 3;
+const SAMPLE_RATE: i32 = 48000;
+// Rate control holds the queued audio around this much. A frame's samples are dropped while
+// four times as much is queued, which only happens after a stall.
+const AUDIO_LATENCY_MS: u32 = 50;
+
+fn read_file(path: &str) -> Vec<u8> {
+    match fs::read(path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("failed to read {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn or_exit<T, E: std::fmt::Display>(result: Result<T, E>, what: &str) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("failed to {}: {}", what, e);
+        process::exit(1);
+    })
+}
+
+fn flush(gameboy: &emgb::GameBoy, save: &mut Option<emgb::SaveFile>) {
+    if let Some(save) = save
+        && let Err(e) = save.flush(|now| gameboy.export_save_at(now).unwrap_or_default())
+    {
+        eprintln!("failed to write {}: {}", save.path().display(), e);
+    }
+}
+
+fn button(keycode: sdl2::keyboard::Keycode) -> Option<emgb::Button> {
+    use sdl2::keyboard::Keycode;
+    match keycode {
+        Keycode::Right => Some(emgb::Button::Right),
+        Keycode::Left => Some(emgb::Button::Left),
+        Keycode::Up => Some(emgb::Button::Up),
+        Keycode::Down => Some(emgb::Button::Down),
+        Keycode::X => Some(emgb::Button::A),
+        Keycode::Z => Some(emgb::Button::B),
+        Keycode::Backspace => Some(emgb::Button::Select),
+        Keycode::Return => Some(emgb::Button::Start),
+        _ => None,
+    }
+}
 
 fn main() {
-    println!("Hello, world!");
+    let mut args: Vec<String> = env::args().collect();
+    let fifo = args.iter().any(|arg| arg == "--fifo");
+    args.retain(|arg| arg != "--fifo");
+    if args.len() < 3 {
+        eprintln!("usage: {} [--fifo] <bootrom> <rom>", args[0]);
+        process::exit(1);
+    }
+    let bootrom = emgb::Bootrom::new(read_file(&args[1]).into_boxed_slice());
+    let cartridge = match emgb::Cartridge::new(read_file(&args[2]).into_boxed_slice()) {
+        Ok(cartridge) => {
+            if let Err(e) = cartridge.verify() {
+                eprintln!("warning: {}: {}", args[2], e);
+            }
+            cartridge
+        }
+        Err(e) => {
+            eprintln!("failed to load {}: {}", args[2], e);
+            process::exit(1);
+        }
+    };
+    let mut gameboy = emgb::GameBoy::new(bootrom);
+    gameboy.insert_cartridge(cartridge);
+    if fifo {
+        gameboy.set_renderer(emgb::Renderer::PixelFifo);
+    }
+
+    let mut save = None;
+    if gameboy.has_battery() {
+        let mut file = emgb::SaveFile::for_rom(path::Path::new(&args[2]));
+        match file.load() {
+            Ok(Some(data)) => gameboy.import_save(&data),
+            Ok(None) => (),
+            Err(e) => {
+                eprintln!("failed to read {}: {}", file.path().display(), e);
+                process::exit(1);
+            }
+        }
+        save = Some(file);
+    }
+
+    // SDL turns SIGINT and SIGTERM into a quit event as well as closing the window, which
+    // gives us a chance to flush.
+    let sdl = or_exit(sdl2::init(), "initialize SDL");
+    let video = or_exit(sdl.video(), "initialize SDL video");
+    let window = or_exit(
+        video
+            .window(
+                "emgb",
+                emgb::SCREEN_WIDTH as u32 * SCALE,
+                emgb::SCREEN_HEIGHT as u32 * SCALE,
+            )
+            .position_centered()
+            .build(),
+        "create window",
+    );
+    let mut canvas = or_exit(window.into_canvas().build(), "create canvas");
+    let creator = canvas.texture_creator();
+    let mut texture = or_exit(
+        creator.create_texture_streaming(
+            sdl2::pixels::PixelFormatEnum::BGR555,
+            emgb::SCREEN_WIDTH as u32,
+            emgb::SCREEN_HEIGHT as u32,
+        ),
+        "create texture",
+    );
+    let audio = or_exit(sdl.audio(), "initialize SDL audio");
+    let queue: sdl2::audio::AudioQueue<f32> = or_exit(
+        audio.open_queue(
+            None,
+            &sdl2::audio::AudioSpecDesired {
+                freq: Some(SAMPLE_RATE),
+                channels: Some(2),
+                samples: None,
+            },
+        ),
+        "open audio device",
+    );
+    let sample_rate = queue.spec().freq as u32;
+    gameboy.set_sample_rate(sample_rate);
+    let target = (sample_rate * AUDIO_LATENCY_MS / 1000) as usize;
+    queue.resume();
+    let mut events = or_exit(sdl.event_pump(), "initialize SDL events");
+
+    let frame = time::Duration::from_nanos(1_000_000_000 * 70224 / 4194304);
+    let mut frames = 0;
+    'running: loop {
+        let start = time::Instant::now();
+        for event in events.poll_iter() {
+            match event {
+                sdl2::event::Event::Quit { .. } => break 'running,
+                sdl2::event::Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => {
+                    if let Some(button) = button(keycode) {
+                        gameboy.press(button);
+                    }
+                }
+                sdl2::event::Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let Some(button) = button(keycode) {
+                        gameboy.release(button);
+                    }
+                }
+                _ => (),
+            }
+        }
+        // queue.size() counts bytes of interleaved stereo f32.
+        let queued = queue.size() as usize / 8;
+        gameboy.adjust_sample_rate(queued, target);
+        gameboy.run_frame();
+        let samples = gameboy.drain_samples();
+        if queued < target * 4 {
+            or_exit(queue.queue_audio(&samples), "queue audio");
+        }
+        or_exit(
+            texture.with_lock(None, |pixels, pitch| {
+                for (y, row) in gameboy.frame().chunks(emgb::SCREEN_WIDTH).enumerate() {
+                    for (x, &color) in row.iter().enumerate() {
+                        let i = y * pitch + x * 2;
+                        pixels[i..i + 2].copy_from_slice(&color.to_le_bytes());
+                    }
+                }
+            }),
+            "update texture",
+        );
+        or_exit(canvas.copy(&texture, None, None), "draw frame");
+        canvas.present();
+        frames += 1;
+        if frames % FLUSH_INTERVAL_FRAMES == 0 {
+            flush(&gameboy, &mut save);
+        }
+        if let Some(rest) = frame.checked_sub(start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+    flush(&gameboy, &mut save);
 }
@@ -18,7 +18,7 @@ impl IO8<Reg8> for cpu::Cpu {
         })
     }
     fn write8(&mut self, _bus: &mut peripherals::Peripherals, dst: Reg8, val: u8) -> Option<()> {
-        Some(match dst {
+        match dst {
             Reg8::A => self.regs.a = val,
             Reg8::B => self.regs.b = val,
             Reg8::C => self.regs.c = val,
@@ -26,24 +26,22 @@ impl IO8<Reg8> for cpu::Cpu {
             Reg8::E => self.regs.e = val,
             Reg8::H => self.regs.h = val,
             Reg8::L => self.regs.l = val,
-        })
+        }
+        Some(())
     }
 }
 impl IO8<Imm8> for cpu::Cpu {
     fn read8(&mut self, bus: &peripherals::Peripherals, _: Imm8) -> Option<u8> {
-        static STEP: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-        static VAL8: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-
-        match STEP.load(std::sync::atomic::Ordering::Relaxed) {
+        match self.ctx.imm.step {
             0 => {
-                VAL8.store(bus.read(self.regs.pc), std::sync::atomic::Ordering::Relaxed);
+                self.ctx.imm.val8 = bus.read(self.regs.pc);
                 self.regs.pc = self.regs.pc.wrapping_add(1);
-                STEP.store(1, std::sync::atomic::Ordering::Relaxed);
+                self.ctx.imm.step = 1;
                 None
             }
             1 => {
-                STEP.store(0, std::sync::atomic::Ordering::Relaxed);
-                Some(VAL8.load(std::sync::atomic::Ordering::Relaxed))
+                self.ctx.imm.step = 0;
+                Some(self.ctx.imm.val8)
             }
             _ => unreachable!(),
         }
@@ -55,45 +53,37 @@ impl IO8<Imm8> for cpu::Cpu {
 }
 impl IO8<Indirect> for cpu::Cpu {
     fn read8(&mut self, bus: &peripherals::Peripherals, src: Indirect) -> Option<u8> {
-        static STEP: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-        static VAL8: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-
-        match STEP.load(std::sync::atomic::Ordering::Relaxed) {
+        match self.ctx.operand.step {
             0 => {
-                VAL8.store(
-                    match src {
-                        Indirect::BC => bus.read(self.regs.bc()),
-                        Indirect::DE => bus.read(self.regs.de()),
-                        Indirect::HL => bus.read(self.regs.hl()),
-                        Indirect::CFF => bus.read(0xff00 | self.regs.c as u16),
-                        Indirect::HLD => {
-                            let addr = self.regs.hl();
-                            self.regs.write_hl(addr.wrapping_sub(1));
-                            bus.read(addr)
-                        }
-                        Indirect::HLI => {
-                            let addr = self.regs.hl();
-                            self.regs.write_hl(addr.wrapping_add(1));
-                            bus.read(addr)
-                        }
-                    },
-                    std::sync::atomic::Ordering::Relaxed,
-                );
-                STEP.store(1, std::sync::atomic::Ordering::Relaxed);
+                self.ctx.operand.val8 = match src {
+                    Indirect::BC => bus.read(self.regs.bc()),
+                    Indirect::DE => bus.read(self.regs.de()),
+                    Indirect::HL => bus.read(self.regs.hl()),
+                    Indirect::CFF => bus.read(0xff00 | self.regs.c as u16),
+                    Indirect::HLD => {
+                        let addr = self.regs.hl();
+                        self.regs.write_hl(addr.wrapping_sub(1));
+                        bus.read(addr)
+                    }
+                    Indirect::HLI => {
+                        let addr = self.regs.hl();
+                        self.regs.write_hl(addr.wrapping_add(1));
+                        bus.read(addr)
+                    }
+                };
+                self.ctx.operand.step = 1;
                 None
             }
             1 => {
-                STEP.store(0, std::sync::atomic::Ordering::Relaxed);
-                Some(VAL8.load(std::sync::atomic::Ordering::Relaxed))
+                self.ctx.operand.step = 0;
+                Some(self.ctx.operand.val8)
             }
             _ => unreachable!(),
         }
     }
 
     fn write8(&mut self, bus: &mut peripherals::Peripherals, dst: Indirect, val: u8) -> Option<()> {
-        static STEP: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-
-        match STEP.load(std::sync::atomic::Ordering::Relaxed) {
+        match self.ctx.operand.step {
             0 => {
                 match dst {
                     Indirect::BC => bus.write(self.regs.bc(), val),
@@ -111,11 +101,11 @@ impl IO8<Indirect> for cpu::Cpu {
                         bus.write(addr, val);
                     }
                 }
-                STEP.store(1, std::sync::atomic::Ordering::Relaxed);
+                self.ctx.operand.step = 1;
                 None
             }
             1 => {
-                STEP.store(0, std::sync::atomic::Ordering::Relaxed);
+                self.ctx.operand.step = 0;
                 Some(())
             }
             _ => unreachable!(),
@@ -124,91 +114,71 @@ impl IO8<Indirect> for cpu::Cpu {
 }
 impl IO8<Direct8> for cpu::Cpu {
     fn read8(&mut self, bus: &peripherals::Peripherals, src: Direct8) -> Option<u8> {
-        static STEP: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-        static VAL8: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-        static VAL16: std::sync::atomic::AtomicU16 = std::sync::atomic::AtomicU16::new(0);
-
-        match STEP.load(std::sync::atomic::Ordering::Relaxed) {
+        match self.ctx.operand.step {
             0 => {
                 if let Some(lo) = self.read8(bus, Imm8) {
-                    VAL8.store(lo, std::sync::atomic::Ordering::Relaxed);
-                    STEP.store(1, std::sync::atomic::Ordering::Relaxed);
+                    self.ctx.operand.val8 = lo;
+                    self.ctx.operand.step = 1;
                     if let Direct8::DFE = src {
-                        VAL16.store(0xff00 | (lo as u16), std::sync::atomic::Ordering::Relaxed);
-                        STEP.store(2, std::sync::atomic::Ordering::Relaxed);
+                        self.ctx.operand.val16 = 0xff00 | (lo as u16);
+                        self.ctx.operand.step = 2;
                     }
+                    return self.read8(bus, src);
                 }
                 None
             }
             1 => {
                 if let Some(hi) = self.read8(bus, Imm8) {
-                    VAL16.store(
-                        u16::from_le_bytes([VAL8.load(std::sync::atomic::Ordering::Relaxed), hi]),
-                        std::sync::atomic::Ordering::Relaxed,
-                    );
-                    STEP.store(2, std::sync::atomic::Ordering::Relaxed);
+                    self.ctx.operand.val16 = u16::from_le_bytes([self.ctx.operand.val8, hi]);
+                    self.ctx.operand.step = 2;
+                    return self.read8(bus, src);
                 }
                 None
             }
             2 => {
-                VAL8.store(
-                    bus.read(VAL16.load(std::sync::atomic::Ordering::Relaxed)),
-                    std::sync::atomic::Ordering::Relaxed,
-                );
-                STEP.store(3, std::sync::atomic::Ordering::Relaxed);
+                self.ctx.operand.val8 = bus.read(self.ctx.operand.val16);
+                self.ctx.operand.step = 3;
                 None
             }
             3 => {
-                STEP.store(0, std::sync::atomic::Ordering::Relaxed);
-                Some(VAL8.load(std::sync::atomic::Ordering::Relaxed))
+                self.ctx.operand.step = 0;
+                Some(self.ctx.operand.val8)
             }
             _ => unreachable!(),
         }
     }
 
     fn write8(&mut self, bus: &mut peripherals::Peripherals, dst: Direct8, val: u8) -> Option<()> {
-        static STEP: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-        static VAL8: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-        static VAL16: std::sync::atomic::AtomicU16 = std::sync::atomic::AtomicU16::new(0);
-
-        match STEP.load(std::sync::atomic::Ordering::Relaxed) {
+        match self.ctx.operand.step {
             0 => {
                 if let Some(lo) = self.read8(bus, Imm8) {
-                    VAL8.store(lo, std::sync::atomic::Ordering::Relaxed);
-                    STEP.store(1, std::sync::atomic::Ordering::Relaxed);
+                    self.ctx.operand.val8 = lo;
+                    self.ctx.operand.step = 1;
                     if let Direct8::DFE = dst {
-                        VAL16.store(0xff00 | (lo as u16), std::sync::atomic::Ordering::Relaxed);
-                        STEP.store(2, std::sync::atomic::Ordering::Relaxed);
+                        self.ctx.operand.val16 = 0xff00 | (lo as u16);
+                        self.ctx.operand.step = 2;
                     }
+                    return self.write8(bus, dst, val);
                 }
                 None
             }
             1 => {
                 if let Some(hi) = self.read8(bus, Imm8) {
-                    VAL16.store(
-                        u16::from_le_bytes([VAL8.load(std::sync::atomic::Ordering::Relaxed), hi]),
-                        std::sync::atomic::Ordering::Relaxed,
-                    );
-                    STEP.store(2, std::sync::atomic::Ordering::Relaxed);
+                    self.ctx.operand.val16 = u16::from_le_bytes([self.ctx.operand.val8, hi]);
+                    self.ctx.operand.step = 2;
+                    return self.write8(bus, dst, val);
                 }
                 None
             }
             2 => {
-                bus.write(VAL16.load(std::sync::atomic::Ordering::Relaxed), val);
-                STEP.store(3, std::sync::atomic::Ordering::Relaxed);
+                bus.write(self.ctx.operand.val16, val);
+                self.ctx.operand.step = 3;
                 None
             }
             3 => {
-                bus.write(
-                    VAL16
-                        .load(std::sync::atomic::Ordering::Relaxed)
-                        .wrapping_add(1),
-                    val.checked_shr(8).unwrap_or(0),
-                );
-                STEP.store(4, std::sync::atomic::Ordering::Relaxed);
-                None
+                self.ctx.operand.step = 0;
+                Some(())
             }
-            4 => Some(STEP.store(0, std::sync::atomic::Ordering::Relaxed)),
             _ => unreachable!(),
         }
     }
@@ -229,43 +199,34 @@ impl IO16<Reg16> for cpu::Cpu {
         })
     }
     fn write16(&mut self, _bus: &mut peripherals::Peripherals, dst: Reg16, val: u16) -> Option<()> {
-        Some(match dst {
+        match dst {
             Reg16::AF => self.regs.write_af(val),
             Reg16::BC => self.regs.write_bc(val),
             Reg16::DE => self.regs.write_de(val),
             Reg16::HL => self.regs.write_hl(val),
             Reg16::SP => self.regs.sp = val,
-        })
+        }
+        Some(())
     }
 }
 impl IO16<Imm16> for cpu::Cpu {
     fn read16(&mut self, bus: &peripherals::Peripherals, _: Imm16) -> Option<u16> {
-        static STEP: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-        static VAL8: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-        static VAL16: std::sync::atomic::AtomicU16 = std::sync::atomic::AtomicU16::new(0);
-
-        match STEP.load(std::sync::atomic::Ordering::Relaxed) {
+        match self.ctx.operand.step {
             0 => {
                 if let Some(lo) = self.read8(bus, Imm8) {
-                    VAL8.store(lo, std::sync::atomic::Ordering::Relaxed);
-                    STEP.store(1, std::sync::atomic::Ordering::Relaxed);
+                    self.ctx.operand.val8 = lo;
+                    self.ctx.operand.step = 1;
+                    return self.read16(bus, Imm16);
                 }
                 None
             }
             1 => {
                 if let Some(hi) = self.read8(bus, Imm8) {
-                    VAL16.store(
-                        u16::from_le_bytes([VAL8.load(std::sync::atomic::Ordering::Relaxed), hi]),
-                        std::sync::atomic::Ordering::Relaxed,
-                    );
-                    STEP.store(2, std::sync::atomic::Ordering::Relaxed);
+                    self.ctx.operand.step = 0;
+                    return Some(u16::from_le_bytes([self.ctx.operand.val8, hi]));
                 }
                 None
             }
-            2 => {
-                STEP.store(0, std::sync::atomic::Ordering::Relaxed);
-                Some(VAL16.load(std::sync::atomic::Ordering::Relaxed))
-            }
             _ => unreachable!(),
         }
     }
@@ -281,44 +242,37 @@ impl IO16<Direct16> for cpu::Cpu {
     }
 
     fn write16(&mut self, bus: &mut peripherals::Peripherals, _: Direct16, val: u16) -> Option<()> {
-        static STEP: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-        static VAL8: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
-        static VAL16: std::sync::atomic::AtomicU16 = std::sync::atomic::AtomicU16::new(0);
-
-        match STEP.load(std::sync::atomic::Ordering::Relaxed) {
+        match self.ctx.operand.step {
             0 => {
                 if let Some(lo) = self.read8(bus, Imm8) {
-                    VAL8.store(lo, std::sync::atomic::Ordering::Relaxed);
-                    STEP.store(1, std::sync::atomic::Ordering::Relaxed);
+                    self.ctx.operand.val8 = lo;
+                    self.ctx.operand.step = 1;
+                    return self.write16(bus, Direct16, val);
                 }
                 None
             }
             1 => {
                 if let Some(hi) = self.read8(bus, Imm8) {
-                    VAL16.store(
-                        u16::from_le_bytes([VAL8.load(std::sync::atomic::Ordering::Relaxed), hi]),
-                        std::sync::atomic::Ordering::Relaxed,
-                    );
-                    STEP.store(2, std::sync::atomic::Ordering::Relaxed);
+                    self.ctx.operand.val16 = u16::from_le_bytes([self.ctx.operand.val8, hi]);
+                    self.ctx.operand.step = 2;
+                    return self.write16(bus, Direct16, val);
                 }
                 None
             }
             2 => {
-                bus.write(VAL16.load(std::sync::atomic::Ordering::Relaxed), val as u8);
-                STEP.store(3, std::sync::atomic::Ordering::Relaxed);
+                bus.write(self.ctx.operand.val16, val as u8);
+                self.ctx.operand.step = 3;
                 None
             }
             3 => {
-                bus.write(
-                    VAL16
-                        .load(std::sync::atomic::Ordering::Relaxed)
-                        .wrapping_add(1),
-                    (val >> 8) as u8,
-                );
-                STEP.store(4, std::sync::atomic::Ordering::Relaxed);
+                bus.write(self.ctx.operand.val16.wrapping_add(1), (val >> 8) as u8);
+                self.ctx.operand.step = 4;
                 None
             }
-            4 => Some(STEP.store(0, std::sync::atomic::Ordering::Relaxed)),
+            4 => {
+                self.ctx.operand.step = 0;
+                Some(())
+            }
             _ => unreachable!(),
         }
     }
@@ -346,6 +300,7 @@ pub enum Reg16 {
 pub struct Imm8;
 #[derive(Clone, Copy, Debug)]
 pub struct Imm16;
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug)]
 pub enum Indirect {
     BC,
@@ -355,6 +310,7 @@ pub enum Indirect {
     HLD,
     HLI,
 }
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug)]
 pub enum Direct8 {
     D,
@@ -524,8 +480,6 @@ mod tests {
         assert_eq!(cpu.read8(&peripherals, Direct8::D), None);
         assert_eq!(cpu.read8(&peripherals, Direct8::D), None);
         assert_eq!(cpu.read8(&peripherals, Direct8::D), None);
-        assert_eq!(cpu.read8(&peripherals, Direct8::D), None);
-        assert_eq!(cpu.read8(&peripherals, Direct8::D), None);
         assert_eq!(cpu.read8(&peripherals, Direct8::D), Some(val_expected));
         assert_eq!(cpu.regs.pc, 2);
     }
@@ -547,9 +501,6 @@ mod tests {
         assert_eq!(cpu.write8(&mut peripherals, Direct8::D, val_expected), None);
         assert_eq!(cpu.write8(&mut peripherals, Direct8::D, val_expected), None);
         assert_eq!(cpu.write8(&mut peripherals, Direct8::D, val_expected), None);
-        assert_eq!(cpu.write8(&mut peripherals, Direct8::D, val_expected), None);
-        assert_eq!(cpu.write8(&mut peripherals, Direct8::D, val_expected), None);
-        assert_eq!(cpu.write8(&mut peripherals, Direct8::D, val_expected), None);
         assert_eq!(
             cpu.write8(&mut peripherals, Direct8::D, val_expected),
             Some(())
@@ -627,8 +578,6 @@ mod tests {
         cpu.regs.pc = 0;
         assert_eq!(cpu.read16(&peripherals, Imm16), None);
         assert_eq!(cpu.read16(&peripherals, Imm16), None);
-        assert_eq!(cpu.read16(&peripherals, Imm16), None);
-        assert_eq!(cpu.read16(&peripherals, Imm16), None);
         assert_eq!(cpu.read16(&peripherals, Imm16), Some(val_expected));
         assert_eq!(cpu.regs.pc, 2);
     }
@@ -652,6 +601,9 @@ mod tests {
         assert_eq!(cpu.write16(&mut peripherals, Direct16, val_expected), None);
         assert_eq!(cpu.write16(&mut peripherals, Direct16, val_expected), None);
         assert_eq!(cpu.write16(&mut peripherals, Direct16, val_expected), None);
-        assert_eq!(cpu.write16(&mut peripherals, Direct16, val_expected), None);
+        assert_eq!(
+            cpu.write16(&mut peripherals, Direct16, val_expected),
+            Some(())
+        );
     }
 }
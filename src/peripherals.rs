@@ -1,39 +1,238 @@
+use crate::apu;
 use crate::bootrom;
+use crate::cartridge;
+use crate::dma;
+use crate::hdma;
 use crate::hram;
+use crate::interrupts;
+use crate::joypad;
+use crate::ppu;
+use crate::timer;
 use crate::wram;
 
+// The CPU sits out this many M-cycles after STOP switches speed.
+const SPEED_SWITCH_MCYCLES: u16 = 2050;
+
 pub struct Peripherals {
     bootrom: bootrom::Bootrom,
+    cgb: bool,
+    dmg_compat: bool,
+    double_speed: bool,
+    speed_armed: bool,
+    speed_switch: u16,
+    // Toggles every M-cycle in double speed so fixed-rate parts can tick every other one.
+    half: bool,
+    cartridge: Option<cartridge::Cartridge>,
     wram: wram::WRam,
     hram: hram::HRam,
+    ppu: ppu::Ppu,
+    apu: apu::Apu,
+    dma: dma::Dma,
+    hdma: hdma::Hdma,
+    timer: timer::Timer,
+    joypad: joypad::Joypad,
+    pub interrupts: interrupts::Interrupts,
 }
 impl Peripherals {
     pub fn new(bootrom: bootrom::Bootrom) -> Self {
         Self {
+            cgb: bootrom.is_cgb(),
+            dmg_compat: false,
+            double_speed: false,
+            speed_armed: false,
+            speed_switch: 0,
+            half: false,
             bootrom,
+            cartridge: None,
             wram: wram::WRam::new(),
             hram: hram::HRam::new(),
+            ppu: ppu::Ppu::new(),
+            apu: apu::Apu::new(),
+            dma: dma::Dma::new(),
+            hdma: hdma::Hdma::new(),
+            timer: timer::Timer::new(),
+            joypad: joypad::Joypad::new(),
+            interrupts: interrupts::Interrupts::new(),
+        }
+    }
+    // CGB hardware runs when the cartridge asks for it or a CGB boot ROM is loaded, which then
+    // drops DMG cartridges into compatibility mode through KEY0.
+    pub fn insert_cartridge(&mut self, cartridge: cartridge::Cartridge) {
+        self.cgb = self.bootrom.is_cgb() || cartridge.header().supports_cgb();
+        self.wram.set_cgb(self.cgb);
+        self.ppu.set_cgb(self.cgb);
+        self.apu.set_cgb(self.cgb);
+        self.cartridge = Some(cartridge);
+    }
+    pub fn cartridge(&self) -> Option<&cartridge::Cartridge> {
+        self.cartridge.as_ref()
+    }
+    pub fn cartridge_mut(&mut self) -> Option<&mut cartridge::Cartridge> {
+        self.cartridge.as_mut()
+    }
+    fn cgb_mode(&self) -> bool {
+        self.cgb && !self.dmg_compat
+    }
+    pub fn double_speed(&self) -> bool {
+        self.double_speed
+    }
+    // Called by STOP, which resets DIV. Returns true if KEY1 was armed and the speed switched
+    // instead of stopping.
+    pub fn stop(&mut self) -> bool {
+        self.write_timer(0xff04, 0);
+        if !self.speed_armed {
+            return false;
+        }
+        self.speed_armed = false;
+        self.double_speed = !self.double_speed;
+        self.speed_switch = SPEED_SWITCH_MCYCLES;
+        self.ppu.set_double_speed(self.double_speed);
+        true
+    }
+    // The CPU is held while HDMA copies a block and while a speed switch settles.
+    pub fn cpu_stalled(&self) -> bool {
+        self.hdma.is_copying() || self.speed_switch > 0
+    }
+    pub fn press(&mut self, button: joypad::Button) {
+        self.joypad.press(&mut self.interrupts, button);
+    }
+    pub fn release(&mut self, button: joypad::Button) {
+        self.joypad.release(&mut self.interrupts, button);
+    }
+    pub fn set_sample_rate(&mut self, rate: u32) {
+        self.apu.set_sample_rate(rate);
+    }
+    pub fn adjust_sample_rate(&mut self, queued: usize, target: usize) {
+        self.apu.adjust_sample_rate(queued, target);
+    }
+    pub fn drain_samples(&mut self) -> Vec<f32> {
+        self.apu.drain_samples()
+    }
+    pub fn set_renderer(&mut self, renderer: ppu::Renderer) {
+        self.ppu.set_renderer(renderer);
+    }
+    pub fn frame(&self) -> &[u16] {
+        self.ppu.frame()
+    }
+    pub fn frames(&self) -> u64 {
+        self.ppu.frames()
+    }
+    // The frame sequencer steps on a falling edge of DIV bit 4, or bit 5 in double speed so it
+    // stays at 512 Hz. Resetting DIV can step it early.
+    fn frame_sequencer_bit(&self) -> bool {
+        let bit = if self.double_speed { 5 } else { 4 };
+        (self.timer.read(0xff04) >> bit) & 1 > 0
+    }
+    fn update_div(&mut self, f: impl FnOnce(&mut Self)) {
+        let old = self.frame_sequencer_bit();
+        f(self);
+        if old && !self.frame_sequencer_bit() {
+            self.apu.step_frame_sequencer();
+        }
+    }
+    fn write_timer(&mut self, addr: u16, val: u8) {
+        self.update_div(|peripherals| peripherals.timer.write(addr, val));
+    }
+    pub fn tick(&mut self) {
+        self.speed_switch = self.speed_switch.saturating_sub(1);
+        self.update_div(|peripherals| peripherals.timer.tick(&mut peripherals.interrupts));
+        if let Some(addr) = self.dma.tick() {
+            let val = self.read_bus(addr);
+            self.dma.set_last(val);
+            self.ppu.write_oam_dma(addr as u8, val);
+        }
+        // HDMA moves 16 bytes per 8 single speed M-cycles at either speed.
+        for _ in 0..if self.double_speed { 1 } else { 2 } {
+            if let Some((source, dest)) = self.hdma.next() {
+                let val = self.read_bus(source);
+                self.ppu.write_vram(0x8000 | dest, val);
+            }
+        }
+        let mode = self.ppu.mode();
+        self.ppu.tick(&mut self.interrupts);
+        if self.ppu.mode() == ppu::Mode::HBlank && mode != ppu::Mode::HBlank {
+            self.hdma.hblank();
+        }
+        self.half = self.double_speed && !self.half;
+        if !self.half {
+            self.apu.tick();
+            if let Some(cartridge) = &mut self.cartridge {
+                cartridge.tick();
+            }
         }
     }
     pub fn read(&self, addr: u16) -> u8 {
+        self.dma
+            .conflict(addr)
+            .unwrap_or_else(|| self.read_bus(addr))
+    }
+    fn read_bus(&self, addr: u16) -> u8 {
         match addr {
-            0x0000..=0x00ff => {
-                if self.bootrom.is_active() {
-                    self.bootrom.read(addr)
-                } else {
-                    0xff
-                }
+            0x0000..=0x00ff if self.bootrom.is_active() => self.bootrom.read(addr),
+            0x0200..=0x08ff if self.bootrom.is_active() && self.bootrom.is_cgb() => {
+                self.bootrom.read(addr)
             }
+            0x0000..=0x7fff | 0xa000..=0xbfff => match &self.cartridge {
+                Some(cartridge) => cartridge.read(addr),
+                None => 0xff,
+            },
+            0x8000..=0x9fff => self.ppu.read_vram(addr),
             0xc000..=0xfdff => self.wram.read(addr),
+            0xfe00..=0xfeff => self.ppu.read_oam(addr),
+            0xff00 => self.joypad.read(),
+            0xff04..=0xff07 => self.timer.read(addr),
+            0xff0f => self.interrupts.read(addr),
+            0xff10..=0xff3f => self.apu.read(addr),
+            0xff40..=0xff45 | 0xff47..=0xff4b | 0xff4f | 0xff68..=0xff6c => self.ppu.read(addr),
+            0xff46 => self.dma.read(),
+            0xff4d if self.cgb_mode() => {
+                0x7e | (self.double_speed as u8) << 7 | self.speed_armed as u8
+            }
+            0xff55 if self.cgb_mode() => self.hdma.read(addr),
+            0xff70 => self.wram.read_svbk(),
             0xff80..=0xfffe => self.hram.read(addr),
+            0xffff => self.interrupts.read(addr),
             _ => 0xff,
         }
     }
     pub fn write(&mut self, addr: u16, val: u8) {
+        if self.dma.conflict(addr).is_some() {
+            return;
+        }
         match addr {
+            0x0000..=0x7fff | 0xa000..=0xbfff => {
+                if let Some(cartridge) = &mut self.cartridge {
+                    cartridge.write(addr, val);
+                }
+            }
+            0x8000..=0x9fff => self.ppu.write_vram(addr, val),
             0xc000..=0xfdff => self.wram.write(addr, val),
+            0xfe00..=0xfeff => self.ppu.write_oam(addr, val),
+            0xff00 => self.joypad.write(&mut self.interrupts, val),
+            0xff04..=0xff07 => self.write_timer(addr, val),
+            0xff0f => self.interrupts.write(addr, val),
+            0xff10..=0xff3f => self.apu.write(addr, val),
+            0xff40..=0xff45 | 0xff47..=0xff4b | 0xff4f | 0xff68..=0xff6c => {
+                self.ppu.write(addr, val)
+            }
+            // KEY0 can only be written by the boot ROM.
+            0xff4c if self.cgb && self.bootrom.is_active() => {
+                self.dmg_compat = val & 0x04 > 0;
+                self.wram.set_cgb(!self.dmg_compat);
+                self.ppu.set_dmg_compat(self.dmg_compat);
+            }
+            0xff46 => self.dma.write(val),
+            0xff4d if self.cgb_mode() => self.speed_armed = val & 0x01 > 0,
+            0xff51..=0xff55 if self.cgb_mode() => {
+                let hblank = self.hdma.write(addr, val);
+                if hblank && !self.ppu.is_enabled() {
+                    self.hdma.hblank();
+                }
+            }
             0xff50 => self.bootrom.write(addr, val),
+            0xff70 => self.wram.write_svbk(val),
             0xff80..=0xfffe => self.hram.write(addr, val),
+            0xffff => self.interrupts.write(addr, val),
             _ => (),
         }
     }
@@ -48,6 +247,178 @@ mod tests {
         let mut peripherals = Peripherals::new(bootrom);
         peripherals.write(0xc000, 42);
         assert_eq!(peripherals.read(0xc000), 42);
+        peripherals.write(0xc080, 43);
+        assert_eq!(peripherals.read(0xc000), 42);
+        peripherals.write(0xdfff, 44);
+        assert_eq!(peripherals.read(0xdfff), 44);
+    }
+
+    #[test]
+    fn test_peripherals_vram_oam() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        peripherals.write(0x8000, 42);
+        peripherals.write(0x9fff, 43);
+        peripherals.write(0xfe00, 44);
+        peripherals.write(0xfea0, 45);
+        assert_eq!(peripherals.read(0x8000), 42);
+        assert_eq!(peripherals.read(0x9fff), 43);
+        assert_eq!(peripherals.read(0xfe00), 44);
+        assert_eq!(peripherals.read(0xfea0), 0x00);
+    }
+
+    #[test]
+    fn test_peripherals_lcd() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        for addr in [
+            0xff42, 0xff43, 0xff45, 0xff47, 0xff48, 0xff49, 0xff4a, 0xff4b,
+        ] {
+            peripherals.write(addr, addr as u8);
+            assert_eq!(peripherals.read(addr), addr as u8);
+        }
+        peripherals.write(0xff44, 0x12);
+        assert_eq!(peripherals.read(0xff44), 0);
+        peripherals.write(0xff41, 0xff);
+        assert_eq!(peripherals.read(0xff41), 0xf8);
+        peripherals.write(0xff40, 0x80);
+        peripherals.tick();
+        assert_eq!(peripherals.read(0xff41), 0xfa);
+        assert_eq!(peripherals.interrupts.int_flags, interrupts::STAT);
+    }
+
+    #[test]
+    fn test_peripherals_dma() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        for i in 0..0xa0 {
+            peripherals.write(0xc100 + i, i as u8);
+        }
+        peripherals.write(0x8000, 0x55);
+        peripherals.write(0xff80, 0x66);
+        peripherals.write(0xff46, 0xc1);
+        assert_eq!(peripherals.read(0xff46), 0xc1);
+        peripherals.tick();
+        peripherals.tick();
+        assert_eq!(peripherals.read(0xfe00), 0xff);
+        assert_eq!(peripherals.read(0xd000), 0x00);
+        assert_eq!(peripherals.read(0x8000), 0x55);
+        assert_eq!(peripherals.read(0xff80), 0x66);
+        peripherals.write(0xc000, 0x77);
+        for _ in 1..0xa0 {
+            peripherals.tick();
+        }
+        assert_eq!(peripherals.read(0xc100), 0x00);
+        assert_eq!(peripherals.read(0xc000), 0x00);
+        for i in 0..0xa0 {
+            assert_eq!(peripherals.read(0xfe00 + i), i as u8);
+        }
+
+        peripherals.write(0xff46, 0xe1);
+        for _ in 0..0xa1 {
+            peripherals.tick();
+        }
+        assert_eq!(peripherals.read(0xfe9f), 0x9f);
+    }
+
+    #[test]
+    fn test_peripherals_joypad() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        assert_eq!(peripherals.read(0xff00), 0xff);
+        peripherals.write(0xff00, 0x20);
+        peripherals.press(joypad::Button::Up);
+        assert_eq!(peripherals.read(0xff00), 0xeb);
+        assert_eq!(peripherals.interrupts.int_flags, interrupts::JOYPAD);
+        peripherals.release(joypad::Button::Up);
+        assert_eq!(peripherals.read(0xff00), 0xef);
+    }
+
+    #[test]
+    fn test_peripherals_frame_sequencer() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        peripherals.write(0xff26, 0x80);
+        peripherals.write(0xff12, 0xf0);
+        peripherals.write(0xff11, 0x3f);
+        peripherals.write(0xff14, 0xc0);
+        for _ in 0..2047 {
+            peripherals.tick();
+        }
+        assert_eq!(peripherals.read(0xff26), 0xf1);
+        peripherals.tick();
+        assert_eq!(peripherals.read(0xff26), 0xf0);
+
+        // Resetting DIV while bit 4 is set steps it too.
+        peripherals.write(0xff11, 0x3f);
+        peripherals.write(0xff14, 0xc0);
+        for _ in 0..3072 {
+            peripherals.tick();
+        }
+        assert_eq!(peripherals.read(0xff26), 0xf1);
+        peripherals.write(0xff04, 0);
+        assert_eq!(peripherals.read(0xff26), 0xf0);
+    }
+
+    #[test]
+    fn test_peripherals_timer() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        peripherals.write(0xff06, 0x10);
+        peripherals.write(0xff05, 0xff);
+        peripherals.write(0xff07, 0x05);
+        assert_eq!(peripherals.read(0xff07), 0xfd);
+        for _ in 0..4 {
+            peripherals.tick();
+        }
+        assert_eq!(peripherals.read(0xff05), 0x00);
+        peripherals.tick();
+        assert_eq!(peripherals.read(0xff05), 0x10);
+        assert_eq!(peripherals.read(0xff06), 0x10);
+        assert_eq!(peripherals.interrupts.int_flags, interrupts::TIMER);
+        for _ in 0..59 {
+            peripherals.tick();
+        }
+        assert_eq!(peripherals.read(0xff04), 0x01);
+        peripherals.stop();
+        assert_eq!(peripherals.read(0xff04), 0x00);
+    }
+
+    #[test]
+    fn test_peripherals_echo_ram() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        peripherals.write(0xc123, 42);
+        assert_eq!(peripherals.read(0xe123), 42);
+        peripherals.write(0xfdff, 43);
+        assert_eq!(peripherals.read(0xddff), 43);
+    }
+
+    #[test]
+    fn test_peripherals_svbk() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        peripherals.write(0xff70, 0x02);
+        peripherals.write(0xd000, 42);
+        assert_eq!(peripherals.read(0xff70), 0xff);
+        peripherals.write(0xff70, 0x01);
+        assert_eq!(peripherals.read(0xd000), 42);
+
+        let mut rom = cartridge::tests::build_rom(0x00, 0x00, 0x00);
+        rom[0x143] = 0x80;
+        cartridge::tests::fix_checksums(&mut rom);
+        peripherals.insert_cartridge(cartridge::Cartridge::new(rom.into_boxed_slice()).unwrap());
+        peripherals.write(0xff70, 0x00);
+        assert_eq!(peripherals.read(0xff70), 0xf8);
+        assert_eq!(peripherals.read(0xd000), 42);
+        peripherals.write(0xff70, 0x07);
+        assert_eq!(peripherals.read(0xff70), 0xff);
+        assert_eq!(peripherals.read(0xd000), 0);
+        peripherals.write(0xd000, 43);
+        assert_eq!(peripherals.read(0xf000), 43);
+        peripherals.write(0xff70, 0x01);
+        assert_eq!(peripherals.read(0xd000), 42);
+        assert_eq!(peripherals.read(0xc000), 0);
     }
 
     #[test]
@@ -58,6 +429,169 @@ mod tests {
         assert_eq!(peripherals.read(0xff80), 84);
     }
 
+    #[test]
+    fn test_peripherals_interrupts() {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        peripherals.write(0xff0f, interrupts::TIMER);
+        peripherals.write(0xffff, interrupts::TIMER | interrupts::VBLANK);
+        assert_eq!(peripherals.read(0xff0f), 0xe0 | interrupts::TIMER);
+        assert_eq!(
+            peripherals.read(0xffff),
+            interrupts::TIMER | interrupts::VBLANK
+        );
+        assert_eq!(peripherals.interrupts.irq(), interrupts::TIMER);
+    }
+
+    #[test]
+    fn test_peripherals_cartridge() {
+        let bootrom = bootrom::Bootrom::new(vec![0x11; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        assert_eq!(peripherals.read(0x0150), 0xff);
+        assert_eq!(peripherals.read(0xa000), 0xff);
+
+        let mut rom = cartridge::tests::build_rom(0x08, 0x00, 0x02);
+        rom[0x0000] = 0x22;
+        rom[0x0150] = 0x33;
+        cartridge::tests::fix_checksums(&mut rom);
+        let cartridge = cartridge::Cartridge::new(rom.into_boxed_slice()).unwrap();
+        peripherals.insert_cartridge(cartridge);
+        assert_eq!(peripherals.read(0x0000), 0x11);
+        assert_eq!(peripherals.read(0x0150), 0x33);
+        peripherals.write(0xa000, 0x44);
+        assert_eq!(peripherals.read(0xa000), 0x44);
+        peripherals.write(0xff50, 1);
+        assert_eq!(peripherals.read(0x0000), 0x22);
+    }
+
+    #[test]
+    fn test_peripherals_cgb_bootrom() {
+        let bootrom = bootrom::Bootrom::new(vec![0x11; 0x900].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        let mut rom = cartridge::tests::build_rom(0x00, 0x00, 0x00);
+        rom[0x0200] = 0x22;
+        cartridge::tests::fix_checksums(&mut rom);
+        peripherals.insert_cartridge(cartridge::Cartridge::new(rom.into_boxed_slice()).unwrap());
+        assert_eq!(peripherals.read(0x0100), 0x00);
+        assert_eq!(peripherals.read(0x0200), 0x11);
+        assert_eq!(peripherals.read(0x08ff), 0x11);
+        assert_eq!(peripherals.read(0xff70), 0xf8);
+        assert_eq!(peripherals.read(0xff4f), 0xfe);
+
+        peripherals.write(0xff4c, 0x04);
+        assert_eq!(peripherals.read(0xff70), 0xff);
+        peripherals.write(0xff50, 0x11);
+        assert_eq!(peripherals.read(0x0200), 0x22);
+        peripherals.write(0xff4c, 0x80);
+        assert_eq!(peripherals.read(0xff70), 0xff);
+    }
+
+    fn cgb_peripherals() -> Peripherals {
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        let mut rom = cartridge::tests::build_rom(0x00, 0x00, 0x00);
+        rom[0x143] = 0x80;
+        cartridge::tests::fix_checksums(&mut rom);
+        peripherals.insert_cartridge(cartridge::Cartridge::new(rom.into_boxed_slice()).unwrap());
+        peripherals
+    }
+
+    #[test]
+    fn test_peripherals_gdma() {
+        let mut peripherals = cgb_peripherals();
+        for i in 0..0x20 {
+            peripherals.write(0xc000 + i, i as u8 + 1);
+        }
+        peripherals.write(0xff4f, 0x01);
+        for (addr, val) in [
+            (0xff51, 0xc0),
+            (0xff52, 0x00),
+            (0xff53, 0x01),
+            (0xff54, 0x00),
+        ] {
+            peripherals.write(addr, val);
+        }
+        peripherals.write(0xff55, 0x01);
+        for _ in 0..16 {
+            assert!(peripherals.cpu_stalled());
+            peripherals.tick();
+        }
+        assert!(!peripherals.cpu_stalled());
+        assert_eq!(peripherals.read(0xff55), 0xff);
+        assert_eq!(peripherals.read(0x8100), 0x01);
+        assert_eq!(peripherals.read(0x811f), 0x20);
+        peripherals.write(0xff4f, 0x00);
+        assert_eq!(peripherals.read(0x8100), 0x00);
+    }
+
+    #[test]
+    fn test_peripherals_hblank_dma() {
+        let mut peripherals = cgb_peripherals();
+        for i in 0..0x30 {
+            peripherals.write(0xc000 + i, i as u8 + 1);
+        }
+        for (addr, val) in [
+            (0xff51, 0xc0),
+            (0xff52, 0x00),
+            (0xff53, 0x00),
+            (0xff54, 0x00),
+        ] {
+            peripherals.write(addr, val);
+        }
+        // With the LCD off the first block goes right away.
+        peripherals.write(0xff55, 0x82);
+        assert!(peripherals.cpu_stalled());
+        for _ in 0..8 {
+            peripherals.tick();
+        }
+        assert!(!peripherals.cpu_stalled());
+        assert_eq!(peripherals.read(0xff55), 0x01);
+        assert_eq!(peripherals.read(0x800f), 0x10);
+        assert_eq!(peripherals.read(0x8010), 0x00);
+
+        peripherals.write(0xff40, 0x80);
+        peripherals.tick();
+        while peripherals.ppu.mode() != ppu::Mode::HBlank {
+            assert!(!peripherals.cpu_stalled());
+            peripherals.tick();
+        }
+        assert!(peripherals.cpu_stalled());
+        for _ in 0..8 {
+            peripherals.tick();
+        }
+        assert_eq!(peripherals.read(0xff55), 0x00);
+        assert_eq!(peripherals.read(0x801f), 0x20);
+        peripherals.write(0xff55, 0x00);
+        assert_eq!(peripherals.read(0xff55), 0x80);
+        while peripherals.ppu.mode() == ppu::Mode::HBlank {
+            peripherals.tick();
+        }
+        while peripherals.ppu.mode() != ppu::Mode::HBlank {
+            peripherals.tick();
+        }
+        assert!(!peripherals.cpu_stalled());
+        assert_eq!(peripherals.read(0x8020), 0x00);
+    }
+
+    #[test]
+    fn test_peripherals_key1() {
+        let mut peripherals = cgb_peripherals();
+        assert_eq!(peripherals.read(0xff4d), 0x7e);
+        assert!(!peripherals.stop());
+        peripherals.write(0xff4d, 0x01);
+        assert_eq!(peripherals.read(0xff4d), 0x7f);
+        assert!(peripherals.stop());
+        assert_eq!(peripherals.read(0xff4d), 0xfe);
+        assert!(peripherals.double_speed());
+        assert!(peripherals.cpu_stalled());
+
+        let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
+        let mut peripherals = Peripherals::new(bootrom);
+        peripherals.write(0xff4d, 0x01);
+        assert_eq!(peripherals.read(0xff4d), 0xff);
+        assert!(!peripherals.stop());
+    }
+
     #[test]
     fn test_peripherals_bootrom() {
         let bootrom = bootrom::Bootrom::new(vec![0; 256].into_boxed_slice());
@@ -0,0 +1,1121 @@
+mod fifo;
+
+use crate::interrupts;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+const DOTS_PER_LINE: u16 = 456;
+const OAM_SCAN_DOTS: u16 = 80;
+const DRAWING_DOTS: u16 = 172;
+const LINES_PER_FRAME: u8 = 154;
+
+const LCD_ENABLE: u8 = 1 << 7;
+const WINDOW_MAP: u8 = 1 << 6;
+const WINDOW_ENABLE: u8 = 1 << 5;
+const TILE_DATA: u8 = 1 << 4;
+const BG_MAP: u8 = 1 << 3;
+const OBJ_SIZE: u8 = 1 << 2;
+const OBJ_ENABLE: u8 = 1 << 1;
+const BG_ENABLE: u8 = 1 << 0;
+
+const LYC_EQUAL: u8 = 1 << 2;
+const HBLANK_SOURCE: u8 = 1 << 3;
+const VBLANK_SOURCE: u8 = 1 << 4;
+const OAM_SOURCE: u8 = 1 << 5;
+const LYC_SOURCE: u8 = 1 << 6;
+
+const PRIORITY: u8 = 1 << 7;
+const Y_FLIP: u8 = 1 << 6;
+const X_FLIP: u8 = 1 << 5;
+const DMG_PALETTE: u8 = 1 << 4;
+const BANK: u8 = 1 << 3;
+const CGB_PALETTE: u8 = 0x07;
+
+const AUTO_INCREMENT: u8 = 1 << 7;
+
+// Greenish DMG shades in the same 15-bit format CGB palette RAM uses, red in the low bits.
+const DMG_COLORS: [u16; 4] = [0x6bfc, 0x3b11, 0x29a6, 0x1061];
+const WHITE: u16 = 0x7fff;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Renderer {
+    // Draws each line in one go at the start of mode 3, which always lasts 172 dots.
+    #[default]
+    Scanline,
+    // Steps the background fetcher and pixel FIFOs every dot, so mode 3 length varies and
+    // register writes made during it take effect mid-line.
+    PixelFifo,
+}
+
+#[derive(Clone, Copy)]
+struct Sprite {
+    y: u8,
+    x: u8,
+    tile: u8,
+    attr: u8,
+    index: u8,
+}
+
+// Attributes come from VRAM bank 1 in CGB mode and are 0 otherwise.
+#[derive(Clone, Copy, Default)]
+struct BgPixel {
+    color: u8,
+    attr: u8,
+}
+
+#[derive(Clone, Copy, Default)]
+struct ObjPixel {
+    color: u8,
+    attr: u8,
+    index: u8,
+}
+
+pub struct Ppu {
+    mode: Mode,
+    renderer: Renderer,
+    fifo: fifo::Fifo,
+    cgb: bool,
+    dmg_compat: bool,
+    double_speed: bool,
+    dot: u16,
+    lcdc: u8,
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+    wy_triggered: bool,
+    window_line: u8,
+    stat_line: bool,
+    stat_irq: bool,
+    blank_frame: bool,
+    vbk: u8,
+    bcps: u8,
+    ocps: u8,
+    opri: u8,
+    bg_palettes: [u8; 0x40],
+    obj_palettes: [u8; 0x40],
+    vram: Box<[u8; 0x4000]>,
+    oam: Box<[u8; 0xa0]>,
+    back: Box<[u16; SCREEN_WIDTH * SCREEN_HEIGHT]>,
+    front: Box<[u16; SCREEN_WIDTH * SCREEN_HEIGHT]>,
+    frames: u64,
+}
+
+impl Ppu {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::HBlank,
+            renderer: Renderer::Scanline,
+            fifo: fifo::Fifo::default(),
+            cgb: false,
+            dmg_compat: false,
+            double_speed: false,
+            dot: 0,
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+            wy_triggered: false,
+            window_line: 0,
+            stat_line: false,
+            stat_irq: false,
+            blank_frame: false,
+            vbk: 0,
+            bcps: 0,
+            ocps: 0,
+            opri: 0,
+            bg_palettes: [0; 0x40],
+            obj_palettes: [0; 0x40],
+            vram: Box::new([0; 0x4000]),
+            oam: Box::new([0; 0xa0]),
+            back: Box::new([DMG_COLORS[0]; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            front: Box::new([DMG_COLORS[0]; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            frames: 0,
+        }
+    }
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb;
+        let blank = self.blank_color();
+        self.front.fill(blank);
+    }
+    // KEY0 on CGB hardware: DMG games are drawn the DMG way but colored through palette RAM.
+    pub fn set_dmg_compat(&mut self, dmg_compat: bool) {
+        self.dmg_compat = dmg_compat;
+        if dmg_compat {
+            self.vbk = 0;
+        }
+    }
+    // The PPU keeps its own clock, so a double speed M-cycle only covers two dots.
+    pub fn set_double_speed(&mut self, double_speed: bool) {
+        self.double_speed = double_speed;
+    }
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.lcdc & LCD_ENABLE > 0
+    }
+    fn cgb_mode(&self) -> bool {
+        self.cgb && !self.dmg_compat
+    }
+    pub fn set_renderer(&mut self, renderer: Renderer) {
+        self.renderer = renderer;
+    }
+    // 15-bit colors row by row, red in bits 0-4, green in 5-9 and blue in 10-14.
+    pub fn frame(&self) -> &[u16] {
+        &self.front[..]
+    }
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+    pub fn tick(&mut self, interrupts: &mut interrupts::Interrupts) {
+        if self.lcdc & LCD_ENABLE > 0 {
+            let dots = if self.double_speed { 2 } else { 4 };
+            for _ in 0..dots {
+                self.tick_dot(interrupts);
+            }
+        }
+        if self.stat_irq {
+            self.stat_irq = false;
+            interrupts.request(interrupts::STAT);
+        }
+    }
+    fn tick_dot(&mut self, interrupts: &mut interrupts::Interrupts) {
+        match (self.ly as usize, self.dot) {
+            (0..SCREEN_HEIGHT, 0) => {
+                if self.ly == self.wy {
+                    self.wy_triggered = true;
+                }
+                self.mode = Mode::OamScan;
+            }
+            (0..SCREEN_HEIGHT, OAM_SCAN_DOTS) => {
+                self.mode = Mode::Drawing;
+                match self.renderer {
+                    Renderer::Scanline => self.render_line(),
+                    Renderer::PixelFifo => self.start_fifo(),
+                }
+            }
+            (0..SCREEN_HEIGHT, d)
+                if d == OAM_SCAN_DOTS + DRAWING_DOTS && self.renderer == Renderer::Scanline =>
+            {
+                self.mode = Mode::HBlank;
+            }
+            (SCREEN_HEIGHT, 0) => {
+                self.mode = Mode::VBlank;
+                // The LCD needs a frame to sync after being switched on and shows nothing.
+                if self.blank_frame {
+                    self.blank_frame = false;
+                } else {
+                    std::mem::swap(&mut self.front, &mut self.back);
+                }
+                self.frames += 1;
+                self.wy_triggered = false;
+                self.window_line = 0;
+                interrupts.request(interrupts::VBLANK);
+            }
+            _ => (),
+        }
+        if self.mode == Mode::Drawing && self.renderer == Renderer::PixelFifo && self.step_fifo() {
+            self.mode = Mode::HBlank;
+        }
+        self.dot += 1;
+        if self.dot == DOTS_PER_LINE {
+            self.dot = 0;
+            self.ly = (self.ly + 1) % LINES_PER_FRAME;
+            self.update_lyc();
+        }
+        self.update_stat();
+    }
+    fn update_lyc(&mut self) {
+        if self.ly == self.lyc {
+            self.stat |= LYC_EQUAL;
+        } else {
+            self.stat &= !LYC_EQUAL;
+        }
+    }
+    // All enabled sources share one interrupt line and only its rising edge requests STAT, so a
+    // source becoming true while another one already holds the line high is lost.
+    fn update_stat(&mut self) {
+        let source = match self.mode {
+            Mode::HBlank => HBLANK_SOURCE,
+            Mode::VBlank => VBLANK_SOURCE,
+            Mode::OamScan => OAM_SOURCE,
+            Mode::Drawing => 0,
+        };
+        let line = self.lcdc & LCD_ENABLE > 0
+            && (self.stat & source > 0
+                || self.stat & (LYC_SOURCE | LYC_EQUAL) == LYC_SOURCE | LYC_EQUAL);
+        if line && !self.stat_line {
+            self.stat_irq = true;
+        }
+        self.stat_line = line;
+    }
+    fn tile_row(&self, bank: u8, tile: u8, row: u8, obj: bool) -> (u8, u8) {
+        let base = if obj || self.lcdc & TILE_DATA > 0 {
+            tile as usize * 16
+        } else {
+            (0x1000 + (tile as i8 as isize) * 16) as usize
+        };
+        let i = bank as usize * 0x2000 + base + row as usize * 2;
+        (self.vram[i], self.vram[i + 1])
+    }
+    fn window_active(&self) -> bool {
+        (self.cgb_mode() || self.lcdc & BG_ENABLE > 0)
+            && self.lcdc & WINDOW_ENABLE > 0
+            && self.wy_triggered
+            && self.wx < 167
+    }
+    // Up to ten sprites on the current line in OAM order, then sorted by X. The sort is
+    // stable, so sprites at the same X keep OAM order.
+    fn select_sprites(&self) -> Vec<Sprite> {
+        let height = self.sprite_height();
+        let mut sprites: Vec<Sprite> = self
+            .oam
+            .chunks(4)
+            .enumerate()
+            .map(|(i, s)| Sprite {
+                y: s[0],
+                x: s[1],
+                tile: s[2],
+                attr: s[3],
+                index: i as u8,
+            })
+            .filter(|s| {
+                let top = s.y as i16 - 16;
+                (top..top + height as i16).contains(&(self.ly as i16))
+            })
+            .take(10)
+            .collect();
+        sprites.sort_by_key(|s| s.x);
+        sprites
+    }
+    // DMG lets the sprite with the smaller X win an overlap; CGB goes by OAM index unless
+    // OPRI asks for the DMG rule.
+    fn x_priority(&self) -> bool {
+        !self.cgb_mode() || self.opri & 0x01 > 0
+    }
+    fn sprite_height(&self) -> u8 {
+        if self.lcdc & OBJ_SIZE > 0 { 16 } else { 8 }
+    }
+    fn sprite_row(&self, sprite: &Sprite) -> (u8, u8) {
+        let height = self.sprite_height();
+        let mut row = (self.ly as i16 - (sprite.y as i16 - 16)) as u8;
+        if sprite.attr & Y_FLIP > 0 {
+            row = height - 1 - row;
+        }
+        let tile = if height == 16 {
+            (sprite.tile & 0xfe) + row / 8
+        } else {
+            sprite.tile
+        };
+        let bank = if self.cgb_mode() && sprite.attr & BANK > 0 {
+            1
+        } else {
+            0
+        };
+        self.tile_row(bank, tile, row % 8, true)
+    }
+    fn sprite_palette(&self, attr: u8) -> u8 {
+        if attr & DMG_PALETTE > 0 {
+            self.obp1
+        } else {
+            self.obp0
+        }
+    }
+    fn map_index(&self, map: u8, x: u8, y: u8) -> usize {
+        let map = if self.lcdc & map > 0 { 0x1c00 } else { 0x1800 };
+        map + (y as usize / 8) * 32 + x as usize / 8
+    }
+    fn map_attr(&self, i: usize) -> u8 {
+        if self.cgb_mode() {
+            self.vram[0x2000 + i]
+        } else {
+            0
+        }
+    }
+    fn bg_tile_row(&self, tile: u8, attr: u8, row: u8) -> (u8, u8) {
+        let row = if attr & Y_FLIP > 0 { 7 - row } else { row };
+        let bank = if attr & BANK > 0 { 1 } else { 0 };
+        self.tile_row(bank, tile, row, false)
+    }
+    fn bg_pixel(&self, map: u8, x: u8, y: u8) -> BgPixel {
+        let i = self.map_index(map, x, y);
+        let attr = self.map_attr(i);
+        let (lo, hi) = self.bg_tile_row(self.vram[i], attr, y % 8);
+        let bit = if attr & X_FLIP > 0 { x % 8 } else { 7 - x % 8 };
+        BgPixel {
+            color: ((hi >> bit) & 1) << 1 | ((lo >> bit) & 1),
+            attr,
+        }
+    }
+    fn palette_color(ram: &[u8; 0x40], palette: u8, color: u8) -> u16 {
+        let i = palette as usize * 8 + color as usize * 2;
+        u16::from_le_bytes([ram[i], ram[i + 1]]) & 0x7fff
+    }
+    fn shade_color(&self, ram: &[u8; 0x40], palette: u8, shade: u8) -> u16 {
+        if self.cgb {
+            Self::palette_color(ram, palette, shade)
+        } else {
+            DMG_COLORS[shade as usize]
+        }
+    }
+    fn blank_color(&self) -> u16 {
+        if self.cgb { WHITE } else { DMG_COLORS[0] }
+    }
+    // Picks the background or sprite pixel and turns it into a color. On CGB, LCDC bit 0 off
+    // puts every sprite on top; otherwise either priority bit hides a sprite behind BG colors
+    // 1-3.
+    fn mix(&self, bg: BgPixel, obj: ObjPixel) -> u16 {
+        let obj_wins = self.lcdc & OBJ_ENABLE > 0
+            && obj.color != 0
+            && (bg.color == 0
+                || (self.cgb_mode() && self.lcdc & BG_ENABLE == 0)
+                || (obj.attr | bg.attr) & PRIORITY == 0);
+        match (obj_wins, self.cgb_mode()) {
+            (true, true) => {
+                Self::palette_color(&self.obj_palettes, obj.attr & CGB_PALETTE, obj.color)
+            }
+            (true, false) => {
+                let shade = (self.sprite_palette(obj.attr) >> (obj.color * 2)) & 0x03;
+                let palette = (obj.attr & DMG_PALETTE) >> 4;
+                self.shade_color(&self.obj_palettes, palette, shade)
+            }
+            (false, true) => {
+                Self::palette_color(&self.bg_palettes, bg.attr & CGB_PALETTE, bg.color)
+            }
+            (false, false) => {
+                let shade = (self.bgp >> (bg.color * 2)) & 0x03;
+                self.shade_color(&self.bg_palettes, 0, shade)
+            }
+        }
+    }
+    fn render_line(&mut self) {
+        let mut bg = [BgPixel::default(); SCREEN_WIDTH];
+        if self.cgb_mode() || self.lcdc & BG_ENABLE > 0 {
+            let window = self.window_active();
+            for (x, pixel) in bg.iter_mut().enumerate() {
+                *pixel = if window && x as u8 + 7 >= self.wx {
+                    self.bg_pixel(WINDOW_MAP, x as u8 + 7 - self.wx, self.window_line)
+                } else {
+                    self.bg_pixel(
+                        BG_MAP,
+                        self.scx.wrapping_add(x as u8),
+                        self.scy.wrapping_add(self.ly),
+                    )
+                };
+            }
+            if window {
+                self.window_line += 1;
+            }
+        }
+        let mut obj = [ObjPixel::default(); SCREEN_WIDTH];
+        if self.lcdc & OBJ_ENABLE > 0 {
+            self.render_sprites(&mut obj);
+        }
+        for (x, (bg, obj)) in bg.into_iter().zip(obj).enumerate() {
+            self.back[self.ly as usize * SCREEN_WIDTH + x] = self.mix(bg, obj);
+        }
+    }
+    // The first opaque sprite pixel in priority order owns the spot, even if it ends up
+    // behind the background.
+    fn render_sprites(&self, obj: &mut [ObjPixel; SCREEN_WIDTH]) {
+        let mut sprites = self.select_sprites();
+        if !self.x_priority() {
+            sprites.sort_by_key(|s| s.index);
+        }
+        for sprite in sprites {
+            let (lo, hi) = self.sprite_row(&sprite);
+            for i in 0..8 {
+                let x = sprite.x as i16 - 8 + i;
+                if !(0..SCREEN_WIDTH as i16).contains(&x) || obj[x as usize].color != 0 {
+                    continue;
+                }
+                let bit = if sprite.attr & X_FLIP > 0 { i } else { 7 - i };
+                obj[x as usize] = ObjPixel {
+                    color: ((hi >> bit) & 1) << 1 | ((lo >> bit) & 1),
+                    attr: sprite.attr,
+                    index: sprite.index,
+                };
+            }
+        }
+    }
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xff40 => self.lcdc,
+            0xff41 => 0x80 | self.stat | self.mode as u8,
+            0xff42 => self.scy,
+            0xff43 => self.scx,
+            0xff44 => self.ly,
+            0xff45 => self.lyc,
+            0xff47 => self.bgp,
+            0xff48 => self.obp0,
+            0xff49 => self.obp1,
+            0xff4a => self.wy,
+            0xff4b => self.wx,
+            0xff4f if self.cgb => 0xfe | self.vbk,
+            0xff68 if self.cgb => 0x40 | self.bcps,
+            0xff69 if self.cgb => self.read_palette(self.bcps, &self.bg_palettes),
+            0xff6a if self.cgb => 0x40 | self.ocps,
+            0xff6b if self.cgb => self.read_palette(self.ocps, &self.obj_palettes),
+            0xff6c if self.cgb => 0xfe | self.opri,
+            0xff4f | 0xff68..=0xff6c => 0xff,
+            _ => unreachable!(),
+        }
+    }
+    pub fn write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0xff40 => {
+                if val & LCD_ENABLE == 0 {
+                    if self.lcdc & LCD_ENABLE > 0 {
+                        let blank = self.blank_color();
+                        self.front.fill(blank);
+                    }
+                    self.dot = 0;
+                    self.ly = 0;
+                    self.mode = Mode::HBlank;
+                    self.wy_triggered = false;
+                    self.window_line = 0;
+                    self.update_lyc();
+                } else if self.lcdc & LCD_ENABLE == 0 {
+                    self.blank_frame = true;
+                }
+                self.lcdc = val;
+                self.update_stat();
+            }
+            0xff41 => {
+                // DMG briefly sees every source enabled while the write goes through, which is
+                // enough to raise STAT in HBlank, VBlank or on an LYC match.
+                if !self.cgb {
+                    self.stat =
+                        (self.stat & LYC_EQUAL) | HBLANK_SOURCE | VBLANK_SOURCE | LYC_SOURCE;
+                    self.update_stat();
+                }
+                self.stat = (self.stat & LYC_EQUAL) | (val & 0x78);
+                self.update_stat();
+            }
+            0xff42 => self.scy = val,
+            0xff43 => self.scx = val,
+            0xff44 => (),
+            0xff45 => {
+                self.lyc = val;
+                self.update_lyc();
+                self.update_stat();
+            }
+            0xff47 => self.bgp = val,
+            0xff48 => self.obp0 = val,
+            0xff49 => self.obp1 = val,
+            0xff4a => self.wy = val,
+            0xff4b => self.wx = val,
+            0xff4f if self.cgb_mode() => self.vbk = val & 0x01,
+            0xff68 if self.cgb => self.bcps = val & 0xbf,
+            0xff69 if self.cgb => Self::write_palette(
+                self.vram_blocked(),
+                &mut self.bcps,
+                &mut self.bg_palettes,
+                val,
+            ),
+            0xff6a if self.cgb => self.ocps = val & 0xbf,
+            0xff6b if self.cgb => Self::write_palette(
+                self.vram_blocked(),
+                &mut self.ocps,
+                &mut self.obj_palettes,
+                val,
+            ),
+            0xff6c if self.cgb => self.opri = val & 0x01,
+            0xff4f | 0xff68..=0xff6c => (),
+            _ => unreachable!(),
+        }
+    }
+    // Palette RAM is out of reach while the PPU draws, but the index still advances.
+    fn read_palette(&self, index: u8, ram: &[u8; 0x40]) -> u8 {
+        if self.vram_blocked() {
+            return 0xff;
+        }
+        ram[(index & 0x3f) as usize]
+    }
+    fn write_palette(blocked: bool, index: &mut u8, ram: &mut [u8; 0x40], val: u8) {
+        if !blocked {
+            ram[(*index & 0x3f) as usize] = val;
+        }
+        if *index & AUTO_INCREMENT > 0 {
+            *index = AUTO_INCREMENT | (index.wrapping_add(1) & 0x3f);
+        }
+    }
+    fn vram_blocked(&self) -> bool {
+        self.mode == Mode::Drawing
+    }
+    fn oam_blocked(&self) -> bool {
+        matches!(self.mode, Mode::OamScan | Mode::Drawing)
+    }
+    pub fn read_vram(&self, addr: u16) -> u8 {
+        if self.vram_blocked() {
+            return 0xff;
+        }
+        self.vram[self.vbk as usize * 0x2000 + (addr & 0x1fff) as usize]
+    }
+    pub fn write_vram(&mut self, addr: u16, val: u8) {
+        if !self.vram_blocked() {
+            self.vram[self.vbk as usize * 0x2000 + (addr & 0x1fff) as usize] = val;
+        }
+    }
+    pub fn read_oam(&self, addr: u16) -> u8 {
+        if self.oam_blocked() {
+            return 0xff;
+        }
+        match addr {
+            0xfe00..=0xfe9f => self.oam[(addr & 0xff) as usize],
+            // The unusable area reads 0x00 on DMG; CGB repeats the upper address nibble.
+            _ if self.cgb => (addr as u8 & 0xf0) | (addr as u8 >> 4),
+            _ => 0x00,
+        }
+    }
+    pub fn write_oam_dma(&mut self, offset: u8, val: u8) {
+        self.oam[offset as usize] = val;
+    }
+    pub fn write_oam(&mut self, addr: u16, val: u8) {
+        if !self.oam_blocked()
+            && let 0xfe00..=0xfe9f = addr
+        {
+            self.oam[(addr & 0xff) as usize] = val;
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    // Tile 1 is solid color 3, tile 2 is solid color 1, tile 3 has color 2 in its leftmost
+    // column only and tile 4 has color 3 in its top row only.
+    pub(super) fn setup() -> (Ppu, interrupts::Interrupts) {
+        let mut ppu = Ppu::new();
+        for i in 0..16 {
+            ppu.vram[0x10 + i] = 0xff;
+            ppu.vram[0x20 + i] = if i % 2 == 0 { 0xff } else { 0x00 };
+            ppu.vram[0x30 + i] = if i % 2 == 0 { 0x00 } else { 0x80 };
+        }
+        ppu.vram[0x40] = 0xff;
+        ppu.vram[0x41] = 0xff;
+        ppu.write(0xff47, 0b11_10_01_00);
+        ppu.write(0xff48, 0b11_10_01_00);
+        ppu.write(0xff49, 0b00_01_10_11);
+        (ppu, interrupts::Interrupts::new())
+    }
+
+    pub(super) fn run_frame(ppu: &mut Ppu, interrupts: &mut interrupts::Interrupts) {
+        let frames = ppu.frames();
+        while ppu.frames() == frames {
+            ppu.tick(interrupts);
+        }
+    }
+
+    // Switches the LCD on and runs the blank frame that follows.
+    pub(super) fn lcd_on(ppu: &mut Ppu, interrupts: &mut interrupts::Interrupts, lcdc: u8) {
+        ppu.write(0xff40, LCD_ENABLE | lcdc);
+        run_frame(ppu, interrupts);
+    }
+
+    // The DMG shade at a pixel.
+    pub(super) fn pixel(ppu: &Ppu, x: usize, y: usize) -> u8 {
+        let color = ppu.frame()[y * SCREEN_WIDTH + x];
+        DMG_COLORS.iter().position(|&c| c == color).unwrap() as u8
+    }
+
+    // Fills one palette through BCPS/BCPD (0xff68) or OCPS/OCPD (0xff6a).
+    pub(super) fn set_palette(ppu: &mut Ppu, index: u16, palette: u8, colors: [u16; 4]) {
+        ppu.write(index, AUTO_INCREMENT | (palette * 8));
+        for color in colors {
+            for byte in color.to_le_bytes() {
+                ppu.write(index + 1, byte);
+            }
+        }
+    }
+
+    fn color(ppu: &Ppu, x: usize, y: usize) -> u16 {
+        ppu.frame()[y * SCREEN_WIDTH + x]
+    }
+
+    pub(super) fn set_sprite(ppu: &mut Ppu, i: usize, y: u8, x: u8, tile: u8, attr: u8) {
+        ppu.oam[i * 4..i * 4 + 4].copy_from_slice(&[y, x, tile, attr]);
+    }
+
+    #[test]
+    fn test_vram_blocking() {
+        let mut ppu = Ppu::new();
+        ppu.write_vram(0x8000, 0x12);
+        ppu.mode = Mode::OamScan;
+        assert_eq!(ppu.read_vram(0x8000), 0x12);
+        ppu.mode = Mode::Drawing;
+        assert_eq!(ppu.read_vram(0x8000), 0xff);
+        ppu.write_vram(0x8000, 0x34);
+        ppu.mode = Mode::VBlank;
+        assert_eq!(ppu.read_vram(0x8000), 0x12);
+    }
+
+    #[test]
+    fn test_oam_blocking() {
+        let mut ppu = Ppu::new();
+        ppu.write_oam(0xfe9f, 0x12);
+        for mode in [Mode::OamScan, Mode::Drawing] {
+            ppu.mode = mode;
+            assert_eq!(ppu.read_oam(0xfe9f), 0xff);
+            ppu.write_oam(0xfe9f, 0x34);
+        }
+        ppu.mode = Mode::HBlank;
+        assert_eq!(ppu.read_oam(0xfe9f), 0x12);
+    }
+
+    #[test]
+    fn test_unusable() {
+        let mut ppu = Ppu::new();
+        ppu.write_oam(0xfea0, 0x12);
+        assert_eq!(ppu.read_oam(0xfea0), 0x00);
+        assert_eq!(ppu.read_oam(0xfeff), 0x00);
+        ppu.set_cgb(true);
+        assert_eq!(ppu.read_oam(0xfea0), 0xaa);
+        assert_eq!(ppu.read_oam(0xfec5), 0xcc);
+        ppu.mode = Mode::OamScan;
+        assert_eq!(ppu.read_oam(0xfea0), 0xff);
+    }
+
+    #[test]
+    fn test_timing() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.write(0xff45, 1);
+        ppu.write(0xff40, LCD_ENABLE);
+        ppu.tick(&mut interrupts);
+        assert_eq!(ppu.read(0xff41) & 0x07, Mode::OamScan as u8);
+        for _ in 0..20 {
+            ppu.tick(&mut interrupts);
+        }
+        assert_eq!(ppu.read(0xff41) & 0x07, Mode::Drawing as u8);
+        for _ in 0..43 {
+            ppu.tick(&mut interrupts);
+        }
+        assert_eq!(ppu.read(0xff41) & 0x07, Mode::HBlank as u8);
+        for _ in 0..51 {
+            ppu.tick(&mut interrupts);
+        }
+        assert_eq!(ppu.read(0xff44), 1);
+        assert_eq!(ppu.read(0xff41) & 0x07, LYC_EQUAL | Mode::OamScan as u8);
+        for _ in 0..114 * 143 {
+            ppu.tick(&mut interrupts);
+        }
+        assert_eq!(ppu.read(0xff44), 144);
+        assert_eq!(ppu.read(0xff41) & 0x03, Mode::VBlank as u8);
+        assert_eq!(interrupts.int_flags, interrupts::VBLANK);
+        assert_eq!(ppu.frames(), 1);
+        for _ in 0..114 * 10 {
+            ppu.tick(&mut interrupts);
+        }
+        assert_eq!(ppu.read(0xff44), 0);
+        assert_eq!(ppu.read(0xff41) & 0x03, Mode::OamScan as u8);
+    }
+
+    fn run_until(ppu: &mut Ppu, interrupts: &mut interrupts::Interrupts, ly: u8, mode: Mode) {
+        while ppu.ly != ly || ppu.mode != mode {
+            ppu.tick(interrupts);
+        }
+    }
+
+    #[test]
+    fn test_stat_interrupt() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.write(0xff41, OAM_SOURCE);
+        interrupts.int_flags = 0;
+        ppu.write(0xff40, LCD_ENABLE);
+        ppu.tick(&mut interrupts);
+        assert_eq!(interrupts.int_flags, interrupts::STAT);
+        interrupts.int_flags = 0;
+        run_until(&mut ppu, &mut interrupts, 0, Mode::HBlank);
+        assert_eq!(interrupts.int_flags, 0);
+        run_until(&mut ppu, &mut interrupts, 1, Mode::OamScan);
+        assert_eq!(interrupts.int_flags, interrupts::STAT);
+
+        // HBlank holds the line high into the next OAM scan, which is then blocked.
+        ppu.write(0xff41, HBLANK_SOURCE | OAM_SOURCE);
+        interrupts.int_flags = 0;
+        run_until(&mut ppu, &mut interrupts, 1, Mode::HBlank);
+        assert_eq!(interrupts.int_flags, interrupts::STAT);
+        interrupts.int_flags = 0;
+        run_until(&mut ppu, &mut interrupts, 2, Mode::Drawing);
+        assert_eq!(interrupts.int_flags, 0);
+
+        ppu.write(0xff41, LYC_SOURCE);
+        ppu.write(0xff45, 5);
+        interrupts.int_flags = 0;
+        run_until(&mut ppu, &mut interrupts, 4, Mode::HBlank);
+        assert_eq!(interrupts.int_flags, 0);
+        run_until(&mut ppu, &mut interrupts, 5, Mode::OamScan);
+        assert_eq!(interrupts.int_flags, interrupts::STAT);
+        interrupts.int_flags = 0;
+        ppu.write(0xff41, LYC_SOURCE | VBLANK_SOURCE);
+        run_until(&mut ppu, &mut interrupts, 144, Mode::VBlank);
+        assert_eq!(interrupts.int_flags, interrupts::VBLANK | interrupts::STAT);
+
+        // Enabling a source whose condition already holds is a rising edge too.
+        ppu.write(0xff41, 0);
+        interrupts.int_flags = 0;
+        ppu.write(0xff45, 144);
+        ppu.tick(&mut interrupts);
+        assert_eq!(interrupts.int_flags, 0);
+        ppu.write(0xff41, LYC_SOURCE);
+        ppu.tick(&mut interrupts);
+        assert_eq!(interrupts.int_flags, interrupts::STAT);
+    }
+
+    #[test]
+    fn test_stat_write_bug() {
+        for cgb in [false, true] {
+            let (mut ppu, mut interrupts) = setup();
+            ppu.set_cgb(cgb);
+            ppu.write(0xff40, LCD_ENABLE);
+            run_until(&mut ppu, &mut interrupts, 0, Mode::Drawing);
+            ppu.write(0xff41, 0);
+            ppu.tick(&mut interrupts);
+            assert_eq!(interrupts.int_flags, 0);
+            run_until(&mut ppu, &mut interrupts, 0, Mode::HBlank);
+            ppu.write(0xff41, 0);
+            ppu.tick(&mut interrupts);
+            let expected = if cgb { 0 } else { interrupts::STAT };
+            assert_eq!(interrupts.int_flags, expected);
+        }
+    }
+
+    #[test]
+    fn test_lcd_off_on() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.vram[0x1800..0x1c00].fill(1);
+        lcd_on(&mut ppu, &mut interrupts, TILE_DATA | BG_ENABLE);
+        assert!(ppu.frame().iter().all(|&color| color == DMG_COLORS[0]));
+        run_frame(&mut ppu, &mut interrupts);
+        assert!(ppu.frame().iter().all(|&color| color == DMG_COLORS[3]));
+
+        run_until(&mut ppu, &mut interrupts, 10, Mode::Drawing);
+        ppu.write(0xff41, HBLANK_SOURCE);
+        ppu.write(0xff40, TILE_DATA | BG_ENABLE);
+        assert!(ppu.frame().iter().all(|&color| color == DMG_COLORS[0]));
+        assert_eq!(ppu.read(0xff44), 0);
+        assert_eq!(ppu.read(0xff41) & 0x03, Mode::HBlank as u8);
+        interrupts.int_flags = 0;
+        for _ in 0..114 * 154 {
+            ppu.tick(&mut interrupts);
+        }
+        assert_eq!(interrupts.int_flags, 0);
+        assert_eq!(ppu.read(0xff44), 0);
+
+        let frames = ppu.frames();
+        lcd_on(&mut ppu, &mut interrupts, TILE_DATA | BG_ENABLE);
+        assert_eq!(ppu.frames(), frames + 1);
+        assert!(ppu.frame().iter().all(|&color| color == DMG_COLORS[0]));
+        run_frame(&mut ppu, &mut interrupts);
+        assert!(ppu.frame().iter().all(|&color| color == DMG_COLORS[3]));
+    }
+
+    #[test]
+    fn test_palette_ram() {
+        let mut ppu = Ppu::new();
+        ppu.write(0xff68, 0x80);
+        ppu.write(0xff69, 0x12);
+        assert_eq!(ppu.read(0xff68), 0xff);
+        assert_eq!(ppu.read(0xff69), 0xff);
+        ppu.set_cgb(true);
+        assert_eq!(ppu.read(0xff68), 0x40);
+        ppu.write(0xff68, 0x80 | 0x3e);
+        ppu.write(0xff69, 0x12);
+        ppu.write(0xff69, 0x34);
+        assert_eq!(ppu.read(0xff68), 0xc0);
+        ppu.write(0xff68, 0x3e);
+        assert_eq!(ppu.read(0xff69), 0x12);
+        ppu.write(0xff69, 0x56);
+        assert_eq!(ppu.read(0xff68), 0x7e);
+        assert_eq!(ppu.read(0xff69), 0x56);
+
+        ppu.write(0xff6a, 0x81);
+        ppu.mode = Mode::Drawing;
+        ppu.write(0xff6b, 0x78);
+        assert_eq!(ppu.read(0xff6a), 0xc2);
+        assert_eq!(ppu.read(0xff6b), 0xff);
+        ppu.mode = Mode::HBlank;
+        ppu.write(0xff6a, 0x01);
+        assert_eq!(ppu.read(0xff6b), 0x00);
+        assert_eq!(ppu.read(0xff69), 0x56);
+    }
+
+    #[test]
+    fn test_vram_banks() {
+        let mut ppu = Ppu::new();
+        ppu.write(0xff4f, 0x01);
+        assert_eq!(ppu.read(0xff4f), 0xff);
+        ppu.write_vram(0x8000, 0x11);
+        ppu.set_cgb(true);
+        assert_eq!(ppu.read(0xff4f), 0xfe);
+        ppu.write(0xff4f, 0x01);
+        assert_eq!(ppu.read(0xff4f), 0xff);
+        assert_eq!(ppu.read_vram(0x8000), 0x00);
+        ppu.write_vram(0x9fff, 0x22);
+        ppu.write(0xff4f, 0x00);
+        assert_eq!(ppu.read_vram(0x8000), 0x11);
+        assert_eq!(ppu.read_vram(0x9fff), 0x00);
+        assert_eq!(ppu.vram[0x3fff], 0x22);
+
+        ppu.write(0xff4f, 0x01);
+        ppu.set_dmg_compat(true);
+        assert_eq!(ppu.read(0xff4f), 0xfe);
+        ppu.write(0xff4f, 0x01);
+        assert_eq!(ppu.read_vram(0x8000), 0x11);
+    }
+
+    #[test]
+    fn test_cgb_background() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.set_cgb(true);
+        set_palette(&mut ppu, 0xff68, 0, [0x00, 0x01, 0x02, 0x03]);
+        set_palette(&mut ppu, 0xff68, 1, [0x10, 0x11, 0x12, 0x13]);
+        ppu.vram[0x2020..0x2030].fill(0xff);
+        ppu.vram[0x1800..0x1804].copy_from_slice(&[1, 3, 4, 2]);
+        ppu.vram[0x3800..0x3804].copy_from_slice(&[0x01, X_FLIP, Y_FLIP, BANK]);
+        lcd_on(&mut ppu, &mut interrupts, TILE_DATA);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(color(&ppu, 0, 0), 0x13);
+        assert_eq!(color(&ppu, 8, 0), 0x00);
+        assert_eq!(color(&ppu, 15, 0), 0x02);
+        assert_eq!(color(&ppu, 16, 0), 0x00);
+        assert_eq!(color(&ppu, 16, 7), 0x03);
+        assert_eq!(color(&ppu, 24, 0), 0x03);
+        assert_eq!(color(&ppu, 32, 0), 0x00);
+    }
+
+    #[test]
+    fn test_cgb_sprites() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.set_cgb(true);
+        set_palette(&mut ppu, 0xff68, 0, [0x00, 0x01, 0x02, 0x03]);
+        set_palette(&mut ppu, 0xff6a, 2, [0x20, 0x21, 0x22, 0x23]);
+        set_palette(&mut ppu, 0xff6a, 3, [0x30, 0x31, 0x32, 0x33]);
+        ppu.vram[0x2020..0x2030].fill(0xff);
+        ppu.vram[0x1840] = 1;
+        ppu.vram[0x3840] = PRIORITY;
+        set_sprite(&mut ppu, 0, 16, 12, 1, 0x02);
+        set_sprite(&mut ppu, 1, 16, 8, 2, 0x03);
+        set_sprite(&mut ppu, 2, 32, 8, 2, 0x02);
+        set_sprite(&mut ppu, 3, 48, 8, 2, BANK | 0x02);
+        lcd_on(
+            &mut ppu,
+            &mut interrupts,
+            TILE_DATA | OBJ_ENABLE | BG_ENABLE,
+        );
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(color(&ppu, 0, 0), 0x31);
+        assert_eq!(color(&ppu, 4, 0), 0x23);
+        assert_eq!(color(&ppu, 0, 16), 0x03);
+        assert_eq!(color(&ppu, 0, 32), 0x23);
+
+        ppu.write(0xff6c, 0x01);
+        ppu.write(0xff40, LCD_ENABLE | TILE_DATA | OBJ_ENABLE);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(color(&ppu, 4, 0), 0x31);
+        assert_eq!(color(&ppu, 0, 16), 0x21);
+    }
+
+    #[test]
+    fn test_dmg_compat() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.set_cgb(true);
+        ppu.set_dmg_compat(true);
+        set_palette(&mut ppu, 0xff68, 0, [0x100, 0x101, 0x102, 0x103]);
+        set_palette(&mut ppu, 0xff6a, 1, [0x110, 0x111, 0x112, 0x113]);
+        ppu.vram[0x1800] = 1;
+        ppu.vram[0x3800] = 0x01;
+        set_sprite(&mut ppu, 0, 16, 16, 1, DMG_PALETTE);
+        set_sprite(&mut ppu, 1, 16, 20, 2, 0x00);
+        lcd_on(
+            &mut ppu,
+            &mut interrupts,
+            TILE_DATA | OBJ_ENABLE | BG_ENABLE,
+        );
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(color(&ppu, 0, 0), 0x103);
+        assert_eq!(color(&ppu, 8, 0), 0x110);
+        assert_eq!(color(&ppu, 15, 0), 0x110);
+        assert_eq!(color(&ppu, 24, 0), 0x100);
+        assert_eq!(ppu.read(0xff4f), 0xfe);
+    }
+
+    #[test]
+    fn test_background() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.vram[0x1800] = 1;
+        ppu.vram[0x1801] = 2;
+        lcd_on(&mut ppu, &mut interrupts, TILE_DATA | BG_ENABLE);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 0, 0), 3);
+        assert_eq!(pixel(&ppu, 8, 7), 1);
+        assert_eq!(pixel(&ppu, 16, 0), 0);
+
+        ppu.write(0xff43, 4);
+        ppu.write(0xff42, 0xfc);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 0, 0), 0);
+        assert_eq!(pixel(&ppu, 0, 4), 3);
+        assert_eq!(pixel(&ppu, 4, 4), 1);
+
+        ppu.write(0xff40, LCD_ENABLE | TILE_DATA);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 4, 4), 0);
+    }
+
+    #[test]
+    fn test_signed_tile_data() {
+        let (mut ppu, mut interrupts) = setup();
+        for i in 0..16 {
+            ppu.vram[0x1000 + 0x10 + i] = 0xff;
+            ppu.vram[0x0ff0 + i] = if i % 2 == 0 { 0xff } else { 0x00 };
+        }
+        ppu.vram[0x1800] = 0x01;
+        ppu.vram[0x1801] = 0xff;
+        lcd_on(&mut ppu, &mut interrupts, BG_ENABLE);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 0, 0), 3);
+        assert_eq!(pixel(&ppu, 8, 0), 1);
+    }
+
+    #[test]
+    fn test_window() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.vram[0x1c00] = 1;
+        ppu.vram[0x1c20] = 2;
+        ppu.write(0xff4a, 10);
+        ppu.write(0xff4b, 87);
+        lcd_on(
+            &mut ppu,
+            &mut interrupts,
+            WINDOW_MAP | WINDOW_ENABLE | TILE_DATA | BG_ENABLE,
+        );
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 79, 10), 0);
+        assert_eq!(pixel(&ppu, 80, 9), 0);
+        assert_eq!(pixel(&ppu, 80, 10), 3);
+        assert_eq!(pixel(&ppu, 80, 18), 1);
+        assert_eq!(pixel(&ppu, 88, 10), 0);
+    }
+
+    #[test]
+    fn test_window_line_counter() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.vram[0x1c00] = 1;
+        ppu.vram[0x1c20] = 2;
+        ppu.write(0xff4b, 7);
+        lcd_on(
+            &mut ppu,
+            &mut interrupts,
+            WINDOW_MAP | WINDOW_ENABLE | TILE_DATA | BG_ENABLE,
+        );
+        for _ in 0..114 * 14 {
+            ppu.tick(&mut interrupts);
+        }
+        ppu.write(0xff4b, 200);
+        for _ in 0..114 * 4 {
+            ppu.tick(&mut interrupts);
+        }
+        ppu.write(0xff4b, 7);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 0, 3), 3);
+        assert_eq!(pixel(&ppu, 0, 5), 0);
+        assert_eq!(pixel(&ppu, 0, 11), 3);
+        assert_eq!(pixel(&ppu, 0, 12), 1);
+    }
+
+    #[test]
+    fn test_sprites() {
+        let (mut ppu, mut interrupts) = setup();
+        set_sprite(&mut ppu, 0, 16, 8, 1, 0x00);
+        set_sprite(&mut ppu, 1, 16, 12, 2, 0x10);
+        set_sprite(&mut ppu, 2, 32, 8, 3, 0x20);
+        set_sprite(&mut ppu, 3, 40, 8, 4, 0x40);
+        lcd_on(&mut ppu, &mut interrupts, TILE_DATA | OBJ_ENABLE);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 0, 0), 3);
+        assert_eq!(pixel(&ppu, 7, 7), 3);
+        assert_eq!(pixel(&ppu, 8, 0), 2);
+        assert_eq!(pixel(&ppu, 12, 0), 0);
+        assert_eq!(pixel(&ppu, 7, 16), 2);
+        assert_eq!(pixel(&ppu, 0, 16), 0);
+        assert_eq!(pixel(&ppu, 0, 31), 3);
+        assert_eq!(pixel(&ppu, 0, 24), 0);
+    }
+
+    #[test]
+    fn test_sprite_priority() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.vram[0x1800] = 2;
+        set_sprite(&mut ppu, 0, 16, 12, 1, 0x80);
+        set_sprite(&mut ppu, 1, 24, 8, 1, 0x00);
+        set_sprite(&mut ppu, 2, 24, 8, 2, 0x10);
+        set_sprite(&mut ppu, 3, 32, 12, 1, 0x00);
+        set_sprite(&mut ppu, 4, 32, 8, 2, 0x10);
+        lcd_on(
+            &mut ppu,
+            &mut interrupts,
+            TILE_DATA | OBJ_ENABLE | BG_ENABLE,
+        );
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 0, 0), 1);
+        assert_eq!(pixel(&ppu, 4, 0), 1);
+        assert_eq!(pixel(&ppu, 8, 0), 3);
+        assert_eq!(pixel(&ppu, 0, 8), 3);
+        assert_eq!(pixel(&ppu, 4, 16), 2);
+        assert_eq!(pixel(&ppu, 8, 16), 3);
+    }
+
+    #[test]
+    fn test_sprite_limit() {
+        let (mut ppu, mut interrupts) = setup();
+        for i in 0..11 {
+            set_sprite(&mut ppu, i, 16, 8 + i as u8 * 8, 1, 0x00);
+        }
+        lcd_on(&mut ppu, &mut interrupts, TILE_DATA | OBJ_ENABLE);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 79, 0), 3);
+        assert_eq!(pixel(&ppu, 80, 0), 0);
+    }
+
+    #[test]
+    fn test_tall_sprites() {
+        let (mut ppu, mut interrupts) = setup();
+        set_sprite(&mut ppu, 0, 16, 8, 3, 0x00);
+        set_sprite(&mut ppu, 1, 16, 16, 3, 0x40);
+        lcd_on(&mut ppu, &mut interrupts, TILE_DATA | OBJ_ENABLE | OBJ_SIZE);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 0, 0), 1);
+        assert_eq!(pixel(&ppu, 1, 0), 1);
+        assert_eq!(pixel(&ppu, 0, 8), 2);
+        assert_eq!(pixel(&ppu, 1, 8), 0);
+        assert_eq!(pixel(&ppu, 0, 15), 2);
+        assert_eq!(pixel(&ppu, 8, 0), 2);
+        assert_eq!(pixel(&ppu, 9, 0), 0);
+        assert_eq!(pixel(&ppu, 8, 8), 1);
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::VecDeque;
+
+use super::{
+    BG_ENABLE, BG_MAP, BgPixel, OBJ_ENABLE, ObjPixel, Ppu, SCREEN_WIDTH, Sprite, WINDOW_MAP, X_FLIP,
+};
+
+// The first tile fetch of every line is thrown away, so pixels start coming out 12 dots
+// into mode 3 rather than 6.
+const STARTUP_DOTS: u8 = 6;
+const FETCH_DOTS: u8 = 6;
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+#[derive(Default)]
+pub(super) struct Fifo {
+    bg: VecDeque<BgPixel>,
+    obj: VecDeque<ObjPixel>,
+    fetch_dot: u8,
+    fetch_x: u8,
+    tile: u8,
+    attr: u8,
+    lo: u8,
+    hi: u8,
+    startup: u8,
+    discard: u8,
+    lx: u8,
+    window: bool,
+    sprites: VecDeque<Sprite>,
+    sprite_dots: u8,
+}
+
+impl Ppu {
+    pub(super) fn start_fifo(&mut self) {
+        self.fifo = Fifo {
+            startup: STARTUP_DOTS,
+            discard: self.scx % 8,
+            sprites: self.select_sprites().into(),
+            ..Fifo::default()
+        };
+    }
+    // Advances mode 3 by one dot, or returns true on the dot after the last pixel went out.
+    pub(super) fn step_fifo(&mut self) -> bool {
+        if self.fifo.lx as usize == SCREEN_WIDTH {
+            if self.fifo.window {
+                self.window_line += 1;
+            }
+            return true;
+        }
+        if self.fifo.startup > 0 {
+            self.fifo.startup -= 1;
+            return false;
+        }
+        if self.fifo.sprite_dots > 0 {
+            self.fifo.sprite_dots -= 1;
+            if self.fifo.sprite_dots == 0 {
+                self.fetch_sprite();
+            }
+            return false;
+        }
+        // A sprite stalls output until the background fetcher has data ready, then takes
+        // another six dots of its own.
+        if self.lcdc & OBJ_ENABLE > 0
+            && let Some(sprite) = self.fifo.sprites.front()
+            && sprite.x <= self.fifo.lx + 8
+        {
+            if self.fifo.bg.is_empty() || self.fifo.fetch_dot < FETCH_DOTS - 1 {
+                self.fetch_bg();
+            } else {
+                self.fifo.sprite_dots = SPRITE_FETCH_DOTS - 1;
+            }
+            return false;
+        }
+        if !self.fifo.window && self.window_active() && self.fifo.lx + 7 >= self.wx {
+            self.fifo.window = true;
+            self.fifo.bg.clear();
+            self.fifo.fetch_dot = 0;
+            self.fifo.fetch_x = 0;
+            self.fifo.discard = 7u8.saturating_sub(self.wx);
+        }
+        if let Some(bg) = self.fifo.bg.pop_front() {
+            if self.fifo.discard > 0 {
+                self.fifo.discard -= 1;
+            } else {
+                let obj = self.fifo.obj.pop_front().unwrap_or_default();
+                self.back[self.ly as usize * SCREEN_WIDTH + self.fifo.lx as usize] =
+                    self.mix(bg, obj);
+                self.fifo.lx += 1;
+            }
+        }
+        self.fetch_bg();
+        false
+    }
+    // Tile number, low and high bitplanes take two dots each, then the row is pushed as soon
+    // as the FIFO runs dry. Registers are read live, so mid-line writes land on the next tile.
+    fn fetch_bg(&mut self) {
+        let (map, x, y) = if self.fifo.window {
+            (WINDOW_MAP, self.fifo.fetch_x * 8, self.window_line)
+        } else {
+            (
+                BG_MAP,
+                (self.scx & 0xf8).wrapping_add(self.fifo.fetch_x * 8),
+                self.scy.wrapping_add(self.ly),
+            )
+        };
+        match self.fifo.fetch_dot {
+            1 => {
+                let i = self.map_index(map, x, y);
+                self.fifo.tile = self.vram[i];
+                self.fifo.attr = self.map_attr(i);
+            }
+            3 => self.fifo.lo = self.bg_tile_row(self.fifo.tile, self.fifo.attr, y % 8).0,
+            5 => self.fifo.hi = self.bg_tile_row(self.fifo.tile, self.fifo.attr, y % 8).1,
+            _ => (),
+        }
+        if self.fifo.fetch_dot < FETCH_DOTS {
+            self.fifo.fetch_dot += 1;
+        }
+        if self.fifo.fetch_dot == FETCH_DOTS && self.fifo.bg.is_empty() {
+            let enabled = self.cgb_mode() || self.lcdc & BG_ENABLE > 0;
+            for i in 0..8 {
+                let bit = if self.fifo.attr & X_FLIP > 0 {
+                    i
+                } else {
+                    7 - i
+                };
+                let pixel = if enabled {
+                    BgPixel {
+                        color: ((self.fifo.hi >> bit) & 1) << 1 | ((self.fifo.lo >> bit) & 1),
+                        attr: self.fifo.attr,
+                    }
+                } else {
+                    BgPixel::default()
+                };
+                self.fifo.bg.push_back(pixel);
+            }
+            self.fifo.fetch_dot = 0;
+            self.fifo.fetch_x = self.fifo.fetch_x.wrapping_add(1);
+        }
+    }
+    // Pixels left of the current position are clipped. A sprite already in the FIFO keeps its
+    // opaque pixels over later ones, unless CGB OAM priority favors the newcomer.
+    fn fetch_sprite(&mut self) {
+        let Some(sprite) = self.fifo.sprites.pop_front() else {
+            return;
+        };
+        let (lo, hi) = self.sprite_row(&sprite);
+        let clipped = (self.fifo.lx + 8 - sprite.x) as usize;
+        for i in clipped..8 {
+            let bit = if sprite.attr & X_FLIP > 0 { i } else { 7 - i };
+            let color = ((hi >> bit) & 1) << 1 | ((lo >> bit) & 1);
+            let j = i - clipped;
+            if self.fifo.obj.len() <= j {
+                self.fifo.obj.push_back(ObjPixel::default());
+            }
+            let old = self.fifo.obj[j];
+            if old.color == 0 || (color != 0 && !self.x_priority() && sprite.index < old.index) {
+                self.fifo.obj[j] = ObjPixel {
+                    color,
+                    attr: sprite.attr,
+                    index: sprite.index,
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{lcd_on, pixel, run_frame, set_palette, set_sprite, setup};
+    use super::super::{LCD_ENABLE, Mode, OBJ_SIZE, Renderer, TILE_DATA, WINDOW_ENABLE};
+    use super::*;
+
+    fn drawing_dots(ppu: &mut Ppu, line: u8) -> u16 {
+        let mut interrupts = crate::interrupts::Interrupts::new();
+        ppu.set_renderer(Renderer::PixelFifo);
+        ppu.write(0xff40, ppu.lcdc | LCD_ENABLE);
+        while ppu.ly != line {
+            ppu.tick_dot(&mut interrupts);
+        }
+        let mut dots = 0;
+        while ppu.ly == line {
+            ppu.tick_dot(&mut interrupts);
+            if ppu.mode == Mode::Drawing {
+                dots += 1;
+            }
+        }
+        dots
+    }
+
+    #[test]
+    fn test_matches_scanline() {
+        for cgb in [false, true] {
+            let (mut ppu, mut interrupts) = setup();
+            ppu.set_cgb(cgb);
+            for i in 0..0x400 {
+                ppu.vram[0x1800 + i] = (i % 5) as u8;
+                ppu.vram[0x1c00 + i] = (i % 3) as u8 + 1;
+                ppu.vram[0x3800 + i] = (i * 37) as u8;
+                ppu.vram[0x3c00 + i] = (i * 11) as u8;
+            }
+            for i in 0..0x60 {
+                ppu.vram[0x2000 + i] = (i * 13) as u8;
+            }
+            for palette in 0..8 {
+                let colors = [0, 1, 2, 3].map(|i| palette as u16 * 0x100 + i);
+                set_palette(&mut ppu, 0xff68, palette, colors);
+                set_palette(&mut ppu, 0xff6a, palette, colors.map(|c| c | 0x1000));
+            }
+            set_sprite(&mut ppu, 0, 20, 3, 3, 0x00);
+            set_sprite(&mut ppu, 1, 30, 40, 1, 0x80);
+            set_sprite(&mut ppu, 2, 30, 44, 2, 0x3b);
+            set_sprite(&mut ppu, 3, 60, 100, 3, 0x6a);
+            set_sprite(&mut ppu, 4, 90, 166, 1, 0x15);
+            set_sprite(&mut ppu, 5, 100, 50, 2, 0x0e);
+            set_sprite(&mut ppu, 6, 100, 46, 1, 0x01);
+            ppu.write(0xff42, 5);
+            ppu.write(0xff43, 13);
+            ppu.write(0xff4a, 70);
+            ppu.write(0xff4b, 90);
+            ppu.write(
+                0xff40,
+                LCD_ENABLE
+                    | WINDOW_MAP
+                    | WINDOW_ENABLE
+                    | TILE_DATA
+                    | OBJ_SIZE
+                    | OBJ_ENABLE
+                    | BG_ENABLE,
+            );
+            run_frame(&mut ppu, &mut interrupts);
+            run_frame(&mut ppu, &mut interrupts);
+            let scanline = ppu.frame().to_vec();
+            ppu.set_renderer(Renderer::PixelFifo);
+            run_frame(&mut ppu, &mut interrupts);
+            assert_eq!(ppu.frame(), &scanline[..], "cgb={}", cgb);
+        }
+    }
+
+    #[test]
+    fn test_mode3_length() {
+        let (mut ppu, _) = setup();
+        assert_eq!(drawing_dots(&mut ppu, 0), 172);
+
+        let (mut ppu, _) = setup();
+        ppu.write(0xff43, 3);
+        assert_eq!(drawing_dots(&mut ppu, 0), 175);
+
+        let (mut ppu, _) = setup();
+        ppu.write(0xff4b, 87);
+        ppu.write(0xff40, WINDOW_ENABLE | BG_ENABLE);
+        assert_eq!(drawing_dots(&mut ppu, 0), 178);
+
+        for (x, penalty) in [(0, 11), (8, 11), (16, 11), (20, 7), (23, 6)] {
+            let (mut ppu, _) = setup();
+            set_sprite(&mut ppu, 0, 16, x, 1, 0x00);
+            ppu.write(0xff40, OBJ_ENABLE);
+            assert_eq!(drawing_dots(&mut ppu, 0), 172 + penalty, "x={}", x);
+        }
+    }
+
+    #[test]
+    fn test_mid_line_palette() {
+        let (mut ppu, mut interrupts) = setup();
+        ppu.vram[0x1800..0x1800 + 20].fill(1);
+        ppu.set_renderer(Renderer::PixelFifo);
+        lcd_on(&mut ppu, &mut interrupts, TILE_DATA | BG_ENABLE);
+        while ppu.ly != 0 {
+            ppu.tick_dot(&mut interrupts);
+        }
+        for _ in 0..80 + 12 + 40 {
+            ppu.tick_dot(&mut interrupts);
+        }
+        ppu.write(0xff47, 0b00_00_00_00);
+        run_frame(&mut ppu, &mut interrupts);
+        assert_eq!(pixel(&ppu, 39, 0), 3);
+        assert_eq!(pixel(&ppu, 40, 0), 0);
+        assert_eq!(pixel(&ppu, 39, 1), 0);
+    }
+}
@@ -91,9 +91,11 @@ mod tests {
     use super::*;
     #[test]
     fn test_af() {
-        let mut regs = Registers::default();
-        regs.a = 0x5a;
-        regs.f = 0xa5;
+        let mut regs = Registers {
+            a: 0x5a,
+            f: 0xa5,
+            ..Default::default()
+        };
         assert_eq!(regs.af(), 0x5aa5);
         regs.write_af(0x5aa5);
         assert_eq!(regs.a, 0x5a);
@@ -102,9 +104,11 @@ mod tests {
 
     #[test]
     fn test_bc() {
-        let mut regs = Registers::default();
-        regs.b = 0x5a;
-        regs.c = 0xa5;
+        let mut regs = Registers {
+            b: 0x5a,
+            c: 0xa5,
+            ..Default::default()
+        };
         assert_eq!(regs.bc(), 0x5aa5);
         regs.write_bc(0x5aa5);
         assert_eq!(regs.b, 0x5a);
@@ -113,9 +117,11 @@ mod tests {
 
     #[test]
     fn test_de() {
-        let mut regs = Registers::default();
-        regs.d = 0x5a;
-        regs.e = 0xa5;
+        let mut regs = Registers {
+            d: 0x5a,
+            e: 0xa5,
+            ..Default::default()
+        };
         assert_eq!(regs.de(), 0x5aa5);
         regs.write_de(0x5aa5);
         assert_eq!(regs.d, 0x5a);
@@ -124,9 +130,11 @@ mod tests {
 
     #[test]
     fn test_hl() {
-        let mut regs = Registers::default();
-        regs.h = 0x5a;
-        regs.l = 0xa5;
+        let mut regs = Registers {
+            h: 0x5a,
+            l: 0xa5,
+            ..Default::default()
+        };
         assert_eq!(regs.hl(), 0x5aa5);
         regs.write_hl(0x5aa5);
         assert_eq!(regs.h, 0x5a);
@@ -135,8 +143,10 @@ mod tests {
 
     #[test]
     fn test_zf() {
-        let mut regs = Registers::default();
-        regs.f = 0b_1000_0000;
+        let mut regs = Registers {
+            f: 0b_1000_0000,
+            ..Default::default()
+        };
         assert!(regs.zf());
         regs.set_zf(false);
         assert!(!regs.zf());
@@ -145,8 +155,10 @@ mod tests {
     }
     #[test]
     fn test_nf() {
-        let mut regs = Registers::default();
-        regs.f = 0b_0100_0000;
+        let mut regs = Registers {
+            f: 0b_0100_0000,
+            ..Default::default()
+        };
         assert!(regs.nf());
         regs.set_nf(false);
         assert!(!regs.nf());
@@ -155,8 +167,10 @@ mod tests {
     }
     #[test]
     fn test_hf() {
-        let mut regs = Registers::default();
-        regs.f = 0b_0010_0000;
+        let mut regs = Registers {
+            f: 0b_0010_0000,
+            ..Default::default()
+        };
         assert!(regs.hf());
         regs.set_hf(false);
         assert!(!regs.hf());
@@ -165,8 +179,10 @@ mod tests {
     }
     #[test]
     fn test_cf() {
-        let mut regs = Registers::default();
-        regs.f = 0b_0001_0000;
+        let mut regs = Registers {
+            f: 0b_0001_0000,
+            ..Default::default()
+        };
         assert!(regs.cf());
         regs.set_cf(false);
         assert!(!regs.cf());
@@ -0,0 +1,97 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub struct SaveFile {
+    path: PathBuf,
+    // The last save written, as built for time 0, or the file as loaded.
+    flushed: Vec<u8>,
+}
+impl SaveFile {
+    pub fn for_rom(rom: &Path) -> Self {
+        Self {
+            path: rom.with_extension("sav"),
+            flushed: Vec::new(),
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => {
+                self.flushed = data.clone();
+                Ok(Some(data))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    // `save` builds the save as of a UNIX time. It is compared as of a fixed time, so a clock
+    // footer's timestamp alone never causes a write and the real time is only stamped on saves
+    // that are written. Writes go to a temporary file that replaces the save only once it is
+    // fully on disk, so an interrupted flush leaves the previous save intact.
+    pub fn flush(&mut self, save: impl Fn(u64) -> Vec<u8>) -> io::Result<bool> {
+        let state = save(0);
+        if state == self.flushed {
+            return Ok(false);
+        }
+        let tmp = self.path.with_extension("sav.tmp");
+        let file = fs::File::create(&tmp)?;
+        io::Write::write_all(&mut &file, &save(crate::cartridge::unix_time()))?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        self.flushed = state;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn rom_path(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("emgb-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("game.gb")
+    }
+
+    #[test]
+    fn test_save_file_path() {
+        let save = SaveFile::for_rom(Path::new("roms/game.gbc"));
+        assert_eq!(save.path(), Path::new("roms/game.sav"));
+    }
+
+    #[test]
+    fn test_save_file_flush() {
+        let rom = rom_path("flush");
+        let mut save = SaveFile::for_rom(&rom);
+        assert_eq!(save.load().unwrap(), None);
+        assert!(save.flush(|_| vec![1, 2, 3]).unwrap());
+        assert!(!save.flush(|_| vec![1, 2, 3]).unwrap());
+        assert!(!rom.with_extension("sav.tmp").exists());
+
+        let mut save = SaveFile::for_rom(&rom);
+        assert_eq!(save.load().unwrap(), Some(vec![1, 2, 3]));
+        assert!(!save.flush(|_| vec![1, 2, 3]).unwrap());
+        assert!(save.flush(|_| vec![4, 5]).unwrap());
+        assert_eq!(fs::read(save.path()).unwrap(), vec![4, 5]);
+        fs::remove_dir_all(rom.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_save_file_timestamp() {
+        let rom = rom_path("timestamp");
+        let mut save = SaveFile::for_rom(&rom);
+        let footer = |ram: u8| move |now: u64| [&[ram][..], &now.to_le_bytes()].concat();
+        assert!(save.flush(footer(1)).unwrap());
+        let written = fs::read(save.path()).unwrap();
+        assert_ne!(written[1..], [0; 8]);
+        assert!(!save.flush(footer(1)).unwrap());
+        assert!(save.flush(footer(2)).unwrap());
+        assert_eq!(fs::read(save.path()).unwrap()[0], 2);
+        fs::remove_dir_all(rom.parent().unwrap()).unwrap();
+    }
+}
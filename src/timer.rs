@@ -0,0 +1,203 @@
+use crate::interrupts;
+
+// Counter bit whose falling edge clocks TIMA, for each TAC clock select.
+const TAC_BITS: [u16; 4] = [9, 3, 5, 7];
+const TAC_ENABLE: u8 = 1 << 2;
+
+// DIV is the upper byte of a 16-bit counter that advances every T-cycle. TIMA counts falling
+// edges of one counter bit ANDed with the enable, so anything that drops that signal (a DIV
+// reset, a TAC change) can clock it too.
+#[derive(Default)]
+pub struct Timer {
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    // TIMA wrapped last cycle and reads 0; TMA goes in and the interrupt fires next cycle.
+    overflow: bool,
+    // TMA was just loaded. TIMA writes are ignored and TMA writes land in TIMA as well.
+    reloading: bool,
+}
+impl Timer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn signal(&self) -> bool {
+        self.tac & TAC_ENABLE > 0 && (self.counter >> TAC_BITS[(self.tac & 0x03) as usize]) & 1 > 0
+    }
+    fn increment(&mut self) {
+        self.tima = self.tima.wrapping_add(1);
+        self.overflow = self.tima == 0;
+    }
+    // Updates the counter and clocks TIMA on a falling edge of the selected signal.
+    fn set_counter(&mut self, counter: u16) {
+        let old = self.signal();
+        self.counter = counter;
+        if old && !self.signal() {
+            self.increment();
+        }
+    }
+    pub fn tick(&mut self, interrupts: &mut interrupts::Interrupts) {
+        self.reloading = false;
+        if self.overflow {
+            self.overflow = false;
+            self.reloading = true;
+            self.tima = self.tma;
+            interrupts.request(interrupts::TIMER);
+        }
+        self.set_counter(self.counter.wrapping_add(4));
+    }
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xff04 => (self.counter >> 8) as u8,
+            0xff05 => self.tima,
+            0xff06 => self.tma,
+            0xff07 => 0xf8 | self.tac,
+            _ => unreachable!(),
+        }
+    }
+    pub fn write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0xff04 => self.set_counter(0),
+            0xff05 if self.reloading => (),
+            0xff05 => {
+                self.tima = val;
+                self.overflow = false;
+            }
+            0xff06 => {
+                self.tma = val;
+                if self.reloading {
+                    self.tima = val;
+                }
+            }
+            0xff07 => {
+                let old = self.signal();
+                self.tac = val & 0x07;
+                if old && !self.signal() {
+                    self.increment();
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(timer: &mut Timer, interrupts: &mut interrupts::Interrupts, n: usize) {
+        for _ in 0..n {
+            timer.tick(interrupts);
+        }
+    }
+
+    #[test]
+    fn test_div() {
+        let mut timer = Timer::new();
+        let mut interrupts = interrupts::Interrupts::new();
+        tick(&mut timer, &mut interrupts, 63);
+        assert_eq!(timer.read(0xff04), 0);
+        tick(&mut timer, &mut interrupts, 1);
+        assert_eq!(timer.read(0xff04), 1);
+        tick(&mut timer, &mut interrupts, 64 * 255);
+        assert_eq!(timer.read(0xff04), 0);
+        tick(&mut timer, &mut interrupts, 10);
+        timer.write(0xff04, 0x12);
+        assert_eq!(timer.read(0xff04), 0);
+        tick(&mut timer, &mut interrupts, 64);
+        assert_eq!(timer.read(0xff04), 1);
+    }
+
+    #[test]
+    fn test_tima_rates() {
+        for (tac, mcycles) in [(0x04, 256), (0x05, 4), (0x06, 16), (0x07, 64)] {
+            let mut timer = Timer::new();
+            let mut interrupts = interrupts::Interrupts::new();
+            timer.write(0xff07, tac);
+            assert_eq!(timer.read(0xff07), 0xf8 | tac);
+            tick(&mut timer, &mut interrupts, mcycles - 1);
+            assert_eq!(timer.read(0xff05), 0, "tac={:#x}", tac);
+            tick(&mut timer, &mut interrupts, 1);
+            assert_eq!(timer.read(0xff05), 1, "tac={:#x}", tac);
+            tick(&mut timer, &mut interrupts, mcycles * 9);
+            assert_eq!(timer.read(0xff05), 10, "tac={:#x}", tac);
+        }
+        let mut timer = Timer::new();
+        let mut interrupts = interrupts::Interrupts::new();
+        timer.write(0xff07, 0x01);
+        tick(&mut timer, &mut interrupts, 100);
+        assert_eq!(timer.read(0xff05), 0);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut timer = Timer::new();
+        let mut interrupts = interrupts::Interrupts::new();
+        timer.write(0xff06, 0x80);
+        timer.write(0xff05, 0xff);
+        timer.write(0xff07, 0x05);
+        tick(&mut timer, &mut interrupts, 4);
+        assert_eq!(timer.read(0xff05), 0x00);
+        assert_eq!(interrupts.int_flags, 0);
+        tick(&mut timer, &mut interrupts, 1);
+        assert_eq!(timer.read(0xff05), 0x80);
+        assert_eq!(interrupts.int_flags, interrupts::TIMER);
+    }
+
+    #[test]
+    fn test_overflow_write() {
+        // Writing TIMA while it reads 0 cancels the reload and the interrupt.
+        let mut timer = Timer::new();
+        let mut interrupts = interrupts::Interrupts::new();
+        timer.write(0xff06, 0x80);
+        timer.write(0xff05, 0xff);
+        timer.write(0xff07, 0x05);
+        tick(&mut timer, &mut interrupts, 4);
+        timer.write(0xff05, 0x42);
+        tick(&mut timer, &mut interrupts, 1);
+        assert_eq!(timer.read(0xff05), 0x42);
+        assert_eq!(interrupts.int_flags, 0);
+
+        // In the reload cycle TIMA writes are lost and TMA writes go through to TIMA.
+        timer.write(0xff04, 0);
+        timer.write(0xff05, 0xff);
+        tick(&mut timer, &mut interrupts, 5);
+        assert_eq!(timer.read(0xff05), 0x80);
+        timer.write(0xff05, 0x42);
+        assert_eq!(timer.read(0xff05), 0x80);
+        timer.write(0xff06, 0x90);
+        assert_eq!(timer.read(0xff05), 0x90);
+        assert_eq!(timer.read(0xff06), 0x90);
+        assert_eq!(interrupts.int_flags, interrupts::TIMER);
+    }
+
+    #[test]
+    fn test_div_write_glitch() {
+        let mut timer = Timer::new();
+        let mut interrupts = interrupts::Interrupts::new();
+        timer.write(0xff07, 0x05);
+        tick(&mut timer, &mut interrupts, 2);
+        assert_eq!(timer.read(0xff05), 0);
+        timer.write(0xff04, 0);
+        assert_eq!(timer.read(0xff05), 1);
+        tick(&mut timer, &mut interrupts, 1);
+        timer.write(0xff04, 0);
+        assert_eq!(timer.read(0xff05), 1);
+    }
+
+    #[test]
+    fn test_tac_write_glitch() {
+        let mut timer = Timer::new();
+        let mut interrupts = interrupts::Interrupts::new();
+        timer.write(0xff07, 0x05);
+        tick(&mut timer, &mut interrupts, 2);
+        timer.write(0xff07, 0x01);
+        assert_eq!(timer.read(0xff05), 1);
+        timer.write(0xff07, 0x05);
+        timer.write(0xff07, 0x04);
+        assert_eq!(timer.read(0xff05), 2);
+        timer.write(0xff07, 0x06);
+        assert_eq!(timer.read(0xff05), 2);
+    }
+}
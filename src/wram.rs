@@ -1,13 +1,39 @@
-pub struct WRam(Box<[u8; 0x80]>);
+pub struct WRam {
+    ram: Box<[u8; 0x8000]>,
+    bank: u8,
+    cgb: bool,
+}
 
 impl WRam {
     pub fn new() -> Self {
-        Self(Box::new([0; 0x80]))
+        Self {
+            ram: Box::new([0; 0x8000]),
+            bank: 0,
+            cgb: false,
+        }
+    }
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb;
+    }
+    // 0xe000-0xfdff echoes 0xc000-0xddff, and SVBK picks the upper 4 KiB bank in CGB mode.
+    fn index(&self, addr: u16) -> usize {
+        let offset = (addr & 0x0fff) as usize;
+        if addr & 0x1000 == 0 {
+            return offset;
+        }
+        let bank = if self.cgb { self.bank.max(1) } else { 1 };
+        bank as usize * 0x1000 + offset
     }
     pub fn read(&self, addr: u16) -> u8 {
-        self.0[(addr as usize) & 0x7f]
+        self.ram[self.index(addr)]
     }
     pub fn write(&mut self, addr: u16, val: u8) {
-        self.0[(addr as usize) & 0x7f] = val;
+        self.ram[self.index(addr)] = val;
+    }
+    pub fn read_svbk(&self) -> u8 {
+        if self.cgb { 0xf8 | self.bank } else { 0xff }
+    }
+    pub fn write_svbk(&mut self, val: u8) {
+        self.bank = val & 0x07;
     }
 }